@@ -1,31 +1,101 @@
 use std::env;
 use std::fs;
-use std::ops::Index;
 
-fn riddle_part_one(file_path: &String) {
-    let text = fs::read_to_string(file_path).expect("Error reading file");
+/// What to do with a line that does not contain a single digit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MissingDigitPolicy {
+    Error,
+    Skip,
+    Zero
+}
+
+impl MissingDigitPolicy {
+    fn from_string(text: &str) -> Self {
+        match text {
+            "error" => MissingDigitPolicy::Error,
+            "skip" => MissingDigitPolicy::Skip,
+            "zero" => MissingDigitPolicy::Zero,
+            _ => panic!("Unknown missing digit policy, expected one of error, skip, zero")
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct LineSummary {
+    parsed: usize,
+    skipped: usize,
+    zeroed: usize
+}
+
+impl LineSummary {
+    /// Applies the policy to the first and last digit found in a line.
+    /// Returns the calibration value the line contributes, if any.
+    fn record(&mut self, line_index: usize, digits: Option<(u32, u32)>, policy: MissingDigitPolicy) -> Option<u32> {
+        match (digits, policy) {
+            (Some((first, last)), _) => {
+                self.parsed += 1;
+                Some(first * 10 + last)
+            },
+            (None, MissingDigitPolicy::Error) => {
+                panic!("Line {} does not contain any digit", line_index + 1);
+            },
+            (None, MissingDigitPolicy::Skip) => {
+                self.skipped += 1;
+                None
+            },
+            (None, MissingDigitPolicy::Zero) => {
+                self.zeroed += 1;
+                Some(0)
+            }
+        }
+    }
+
+    fn print(&self) {
+        println!("Lines parsed: {}, skipped: {}, counted as zero: {}", self.parsed, self.skipped, self.zeroed);
+    }
+}
 
-    let lines : Vec<&str> = text.split('\n').collect();
+/// The lines of the input, without the empty one after a trailing newline.
+fn input_lines(text: &str) -> Vec<&str> {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    text.split('\n').collect()
+}
+
+/// Calibration values of part one, made of the first and last digit of each line.
+fn part_one_values(text: &str, policy: MissingDigitPolicy) -> (Vec<u32>, LineSummary) {
+    let lines = input_lines(text);
     let mut numbers : Vec<u32> = Vec::new();
+    let mut summary = LineSummary::default();
 
-    for line in lines {
+    for (line_index, line) in lines.iter().enumerate() {
         let mut chrs = line.chars();
 
-        let first_digit: u32 = chrs.clone().find(|c| c.is_digit(10)).unwrap_or('0').to_digit(10).unwrap();
-        let last_digit: u32 = chrs.rfind(|c| c.is_digit(10)).unwrap_or('0').to_digit(10).unwrap();
+        let first_digit = chrs.clone().find(|c| c.is_ascii_digit()).and_then(|c| c.to_digit(10));
+        let last_digit = chrs.rfind(|c| c.is_ascii_digit()).and_then(|c| c.to_digit(10));
 
-        numbers.push(first_digit * 10 + last_digit);
+        if let Some(number) = summary.record(line_index, first_digit.zip(last_digit), policy) {
+            numbers.push(number);
+        }
     }
+    (numbers, summary)
+}
+
+fn riddle_part_one(file_path: &String, policy: MissingDigitPolicy) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let (numbers, summary) = part_one_values(&text, policy);
     let sum : u32= numbers.into_iter().sum();
+    summary.print();
     println!("{:?}", sum);
 }
 
-fn riddle_part_two(file_path: &String) {
+fn riddle_part_two(file_path: &String, policy: MissingDigitPolicy) {
    
     let text = fs::read_to_string(file_path).expect("Error reading file");
 
-    let lines : Vec<&str> = text.split('\n').collect();
+    let lines = input_lines(&text);
     let mut numbers : Vec<u32> = Vec::new();
+    let mut summary = LineSummary::default();
 
     
     let number_words = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
@@ -42,7 +112,7 @@ fn riddle_part_two(file_path: &String) {
             SearchDir::Left => s.len(),
             SearchDir::Right => 0
         };
-        let mut digit_value:u32 = 0;
+        let mut digit_value: Option<u32> = None;
 
         // Search for word text
         for (word_index, number_word) in number_words.iter().enumerate() {
@@ -56,13 +126,13 @@ fn riddle_part_two(file_path: &String) {
                     SearchDir::Left => {
                         if index <= first_index {
                             first_index = index;
-                            digit_value = word_index as u32 + 1;
+                            digit_value = Some(word_index as u32 + 1);
                         }
                     },
                     SearchDir::Right => {
                         if index >= first_index {
                             first_index = index;
-                            digit_value = word_index as u32 + 1;
+                            digit_value = Some(word_index as u32 + 1);
                         }
                     }
                 };
@@ -71,21 +141,19 @@ fn riddle_part_two(file_path: &String) {
 
         // Search for the digits
         let search_res = match dir {
-            SearchDir::Left => s.find(|x:char| x.is_digit(10)),
-            SearchDir::Right =>s.rfind(|x: char| x.is_digit(10))
+            SearchDir::Left => s.find(|x:char| x.is_ascii_digit()),
+            SearchDir::Right =>s.rfind(|x: char| x.is_ascii_digit())
         };
         if let Some(index) = search_res {
             match dir {
                 SearchDir::Left => {
                     if index <= first_index {
-                        first_index = index;
-                        digit_value = s.chars().nth(index).unwrap().to_digit(10).unwrap();
+                        digit_value = s.chars().nth(index).unwrap().to_digit(10);
                     }
                 },
                 SearchDir::Right => {
                     if index >= first_index {
-                        first_index = index;
-                        digit_value = s.chars().nth(index).unwrap().to_digit(10).unwrap();
+                        digit_value = s.chars().nth(index).unwrap().to_digit(10);
                     }
                 }
             }
@@ -94,22 +162,24 @@ fn riddle_part_two(file_path: &String) {
         digit_value
     };
 
-    for line in lines {
+    for (line_index, line) in lines.iter().enumerate() {
         let left = get_first_number(line, SearchDir::Left);
         let right = get_first_number(line, SearchDir::Right);
 
-        let number = left * 10 + right;
-        println!("{:?} => {:?}", line, number);
-        numbers.push(number);
+        if let Some(number) = summary.record(line_index, left.zip(right), policy) {
+            println!("{:?} => {:?}", line, number);
+            numbers.push(number);
+        }
     }
     let sum : u32= numbers.into_iter().sum();
+    summary.print();
     println!("{:?}", sum);
 
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
+    if args.len() != 3 && args.len() != 5 {
         panic!("Provide the input text file!");
     }
     let riddle_num: u32 = args
@@ -118,15 +188,61 @@ fn main() {
         .parse()
         .expect("Error parsing riddle num");
 
+    let policy = match args.get(3).map(|s| s.as_str()) {
+        Some("--missing-digits") => MissingDigitPolicy::from_string(args.get(4).unwrap()),
+        Some(_) => panic!("Unknown option, expected --missing-digits error|skip|zero"),
+        None => MissingDigitPolicy::Zero
+    };
+
     match riddle_num {
         1 => {
-            riddle_part_one(args.get(2).unwrap());
+            riddle_part_one(args.get(2).unwrap(), policy);
         }
         2 => {
-            riddle_part_two(args.get(2).unwrap());
+            riddle_part_two(args.get(2).unwrap(), policy);
         }
         _ => {
             panic!("Unknown riddle part number");
         }
     };
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary_counts(summary: &LineSummary) -> (usize, usize, usize) {
+        (summary.parsed, summary.skipped, summary.zeroed)
+    }
+
+    #[test]
+    fn error_policy_ignores_trailing_newline() {
+        let (numbers, summary) = part_one_values("a1b\n", MissingDigitPolicy::Error);
+        assert_eq!(numbers, vec![11]);
+        assert_eq!(summary_counts(&summary), (1, 0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Line 2 does not contain any digit")]
+    fn error_policy_rejects_lines_without_digits() {
+        part_one_values("a1b\nxyz\n3c\n", MissingDigitPolicy::Error);
+    }
+
+    #[test]
+    fn skip_policy_counts_only_real_lines() {
+        let (numbers, summary) = part_one_values("1abc\n\n2x\n", MissingDigitPolicy::Skip);
+        assert_eq!(numbers, vec![11, 22]);
+        assert_eq!(summary_counts(&summary), (2, 1, 0));
+    }
+
+    #[test]
+    fn zero_policy_counts_only_real_lines() {
+        let (numbers, summary) = part_one_values("a1b\n", MissingDigitPolicy::Zero);
+        assert_eq!(numbers, vec![11]);
+        assert_eq!(summary_counts(&summary), (1, 0, 0));
+
+        let (numbers, summary) = part_one_values("a1b\nxyz", MissingDigitPolicy::Zero);
+        assert_eq!(numbers, vec![11, 0]);
+        assert_eq!(summary_counts(&summary), (1, 0, 1));
+    }
+}
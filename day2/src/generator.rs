@@ -0,0 +1,120 @@
+use crate::{Bag, ColorSet, CubeColor, CubeDraw, Game};
+
+/// Small seedable PRNG (SplitMix64), so generated inputs can be reproduced.
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `low..=high`.
+    fn between(&mut self, low: usize, high: usize) -> usize {
+        low + (self.next_u64() % (high - low + 1) as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.between(0, i);
+            items.swap(i, j);
+        }
+    }
+}
+
+pub struct GeneratorParams {
+    pub games: usize,
+    pub draws: usize,
+    pub colors: Vec<String>,
+    /// Fraction of the games that are possible with the bag.
+    pub feasible: f64,
+    pub seed: u64
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            draws: 3,
+            colors: vec![String::from("red"), String::from("green"), String::from("blue")],
+            feasible: 0.5,
+            seed: 0
+        }
+    }
+}
+
+/// Answers of both riddle parts for a generated input.
+pub struct Expected {
+    pub possible_id_sum: usize,
+    pub cube_power_sum: usize
+}
+
+fn feasible_draw(rng: &mut Rng, colors: &[CubeColor], bag: &Bag) -> CubeDraw {
+    let mut drawn : Vec<CubeColor> = colors.iter().copied().filter(|color| bag.count(*color) > 0).collect();
+    rng.shuffle(&mut drawn);
+    drawn.truncate(rng.between(1, drawn.len()));
+
+    CubeDraw {
+        cubes: drawn.into_iter().map(|color| (color, rng.between(1, bag.count(color)))).collect()
+    }
+}
+
+/// Raises one color of one draw above what the bag holds.
+fn break_game(rng: &mut Rng, game: &mut Game, colors: &[CubeColor], bag: &Bag) {
+    let draw_index = rng.between(0, game.cube_draws.len() - 1);
+    let color = colors[rng.between(0, colors.len() - 1)];
+    let count = bag.count(color) + rng.between(1, 5);
+
+    let draw = &mut game.cube_draws[draw_index];
+    match draw.cubes.iter_mut().find(|(cube_color, _)| *cube_color == color) {
+        Some(cube) => cube.1 = count,
+        None => draw.cubes.push((color, count))
+    }
+}
+
+/// Generates random games where the requested fraction is possible with `bag`.
+pub fn generate(params: &GeneratorParams, colors: &mut ColorSet, bag: &Bag) -> (Vec<Game>, Expected) {
+    let color_list : Vec<CubeColor> = params.colors.iter().map(|name| colors.intern(name)).collect();
+    if !color_list.iter().any(|color| bag.count(*color) > 0) {
+        panic!("The bag must contain at least one of the generated colors");
+    }
+    if params.draws == 0 {
+        panic!("Games need at least one draw");
+    }
+
+    let mut rng = Rng::new(params.seed);
+
+    let feasible_count = (params.games as f64 * params.feasible.clamp(0.0, 1.0)).round() as usize;
+    let mut is_feasible : Vec<bool> = (0..params.games).map(|game_index| game_index < feasible_count).collect();
+    rng.shuffle(&mut is_feasible);
+
+    let mut games : Vec<Game> = Vec::new();
+    for (game_index, feasible) in is_feasible.into_iter().enumerate() {
+        let mut game = Game {
+            id: game_index + 1,
+            cube_draws: (0..params.draws).map(|_| feasible_draw(&mut rng, &color_list, bag)).collect()
+        };
+        if !feasible {
+            break_game(&mut rng, &mut game, &color_list, bag);
+        }
+        games.push(game);
+    }
+
+    let expected = Expected {
+        possible_id_sum: games.iter().filter(|game| game.is_possible(bag)).map(|game| game.id).sum(),
+        cube_power_sum: games.iter().map(|game| game.minimal_bag().power()).sum()
+    };
+
+    (games, expected)
+}
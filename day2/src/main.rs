@@ -1,32 +1,63 @@
 use std::env;
+use std::fmt;
 use std::fs;
 use std::collections::HashMap;
 
-#[derive(Eq, Hash, PartialEq, Debug)]
-enum CubeColor {
-    Red,
-    Green,
-    Blue
+mod generator;
+mod optimize;
+mod stats;
+
+/// Compact ID of a cube color, handed out by a `ColorSet`.
+#[derive(Eq, Hash, PartialEq, Debug, Clone, Copy, PartialOrd, Ord)]
+struct CubeColor(usize);
+
+/// Interns color names in the order they are first seen in the input.
+#[derive(Debug, Default)]
+struct ColorSet {
+    names: Vec<String>
 }
 
-impl CubeColor {
-    fn from_string(text: &str) -> Self {
-       if text == "red" {
-           CubeColor::Red
-       } else if text == "green" {
-           CubeColor::Green
-       } else if text == "blue" {
-           CubeColor::Blue
-       } else {
-            panic!("Unexpected COLOR");
-       } 
+impl ColorSet {
+    fn intern(&mut self, name: &str) -> CubeColor {
+        match self.get(name) {
+            Some(color) => color,
+            None => {
+                self.names.push(name.to_string());
+                CubeColor(self.names.len() - 1)
+            }
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<CubeColor> {
+        self.names.iter().position(|n| n == name).map(CubeColor)
+    }
+
+    fn name(&self, color: CubeColor) -> &str {
+        &self.names[color.0]
+    }
+
+    fn iter(&self) -> impl Iterator<Item = CubeColor> {
+        (0..self.names.len()).map(CubeColor)
     }
+
+    /// Pairs a value with the color names needed to print it.
+    fn show<'a, T>(&'a self, value: &'a T) -> Named<'a, T> {
+        Named {
+            value,
+            colors: self
+        }
+    }
+}
+
+struct Named<'a, T> {
+    value: &'a T,
+    colors: &'a ColorSet
 }
 
 
 #[derive(Debug)]
 struct CubeDraw {
-    cubes: HashMap<CubeColor, usize>
+    cubes: Vec<(CubeColor, usize)>
 }
 
 #[derive(Debug)]
@@ -36,15 +67,15 @@ struct Game {
 }
 
 impl CubeDraw {
-    fn from_string(text : &str) -> Self {
-        let mut cubes : HashMap<CubeColor, usize> = HashMap::default();
+    fn from_string(text : &str, colors: &mut ColorSet) -> Self {
+        let mut cubes : Vec<(CubeColor, usize)> = Vec::new();
 
-        let draw_infos : Vec<&str> = text.split(",").collect();
+        let draw_infos : Vec<&str> = text.split(',').collect();
         for draw_info in draw_infos {
-            let draw_components : Vec<&str> = draw_info.split(" ").filter(|&x| !x.is_empty()).collect();
+            let draw_components : Vec<&str> = draw_info.split(' ').filter(|&x| !x.is_empty()).collect();
             let count = draw_components[0].parse::<usize>().unwrap();
-            let color = CubeColor::from_string(draw_components[1]);
-            cubes.insert(color, count);
+            let color = colors.intern(draw_components[1]);
+            cubes.push((color, count));
         }
 
 
@@ -52,55 +83,243 @@ impl CubeDraw {
             cubes
         }
     }
+
+    /// Cubes of the given color in this draw, summed if the color is listed twice.
+    fn count(&self, color: CubeColor) -> usize {
+        self.cubes.iter().filter(|(cube_color, _)| *cube_color == color).map(|(_, cube_count)| cube_count).sum()
+    }
+}
+
+impl fmt::Display for Named<'_, CubeDraw> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cubes : Vec<String> = self.value.cubes.iter().map(|(color, count)| format!("{} {}", count, self.colors.name(*color))).collect();
+        write!(f, "{}", cubes.join(", "))
+    }
+}
+
+impl fmt::Display for Named<'_, Game> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let draws : Vec<String> = self.value.cube_draws.iter().map(|draw| self.colors.show(draw).to_string()).collect();
+        write!(f, "Game {}: {}", self.value.id, draws.join("; "))
+    }
 }
 
 impl Game {
-    fn from_string(text: &str) -> Result<Self, ()> {
-        let game_parts  : Vec<&str>  = text.split(":").collect();
+    fn from_string(text: &str, colors: &mut ColorSet) -> Result<Self, ()> {
+        let game_parts  : Vec<&str>  = text.split(':').collect();
         if game_parts.len() != 2 {
             return Err(());
         }
 
         let game_id = game_parts[0][5..].parse::<usize>().unwrap();
-        let cube_draws : Vec<CubeDraw> = game_parts[1].split(";").map(|x: &str| 
-            CubeDraw::from_string(x)
+        let cube_draws : Vec<CubeDraw> = game_parts[1].split(';').map(|x: &str| 
+            CubeDraw::from_string(x, colors)
         ).collect();
     
 
-        return Ok(Self {
+        Ok(Self {
             id: game_id,
             cube_draws
-        });
+        })
+    }
+
+    /// Finds the first draw that the bag can not supply.
+    /// A color listed twice in one draw counts with the sum of both entries.
+    fn check(&self, bag: &Bag) -> Feasibility {
+        for (draw_index, draw) in self.cube_draws.iter().enumerate() {
+            for (cube_color, _) in &draw.cubes {
+                let requested = draw.count(*cube_color);
+                let available = bag.count(*cube_color);
+                if requested > available {
+                    return Feasibility::Impossible {
+                        draw_index,
+                        color: *cube_color,
+                        requested,
+                        available
+                    };
+                }
+            }
+        }
+        Feasibility::Possible
+    }
+
+    fn is_possible(&self, bag: &Bag) -> bool {
+        self.check(bag) == Feasibility::Possible
+    }
+
+    /// The fewest cubes of each color that make this game possible.
+    fn minimal_bag(&self) -> Bag {
+        let mut cubes : HashMap<CubeColor, usize> = HashMap::new();
+
+        for draw in &self.cube_draws {
+            for (cube_color, _) in &draw.cubes {
+                let cube_count = draw.count(*cube_color);
+                if cube_count > *cubes.get(cube_color).unwrap_or(&0) {
+                    cubes.insert(*cube_color, cube_count);
+                }
+            }
+        }
+
+        Bag {
+            cubes
+        }
+    }
+}
+
+fn parse_games(text: &str, colors: &mut ColorSet) -> Vec<Game> {
+    text.split('\n').map(|line| Game::from_string(line, colors).expect("Error parsing game")).collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum Feasibility {
+    Possible,
+    Impossible {
+        draw_index: usize,
+        color: CubeColor,
+        requested: usize,
+        available: usize
     }
 }
 
+const DEFAULT_BAG : &str = "red=12,green=13,blue=14";
+
+/// Cubes loaded into the bag, e.g. `red=12,green=13,blue=14`.
+/// Colors that are not part of the loadout count as zero cubes.
+#[derive(Debug)]
+struct Bag {
+    cubes: HashMap<CubeColor, usize>
+}
+
+impl Bag {
+    fn from_string(text: &str, colors: &mut ColorSet) -> Self {
+        let mut cubes : HashMap<CubeColor, usize> = HashMap::new();
+
+        for entry in text.split([',', '\n']).map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            let (color_name, count) = entry.split_once('=').expect("Bag entry must look like color=count");
+            let count = count.trim().parse::<usize>().expect("Error parsing bag cube count");
+            cubes.insert(colors.intern(color_name.trim()), count);
+        }
+
+        Self {
+            cubes
+        }
+    }
+
+    fn count(&self, color: CubeColor) -> usize {
+        *self.cubes.get(&color).unwrap_or(&0)
+    }
+
+    /// Adds cubes until every draw the other bag can supply fits into this one.
+    fn cover(&mut self, other: &Bag) {
+        for (cube_color, cube_count) in &other.cubes {
+            if *cube_count > self.count(*cube_color) {
+                self.cubes.insert(*cube_color, *cube_count);
+            }
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.cubes.values().sum()
+    }
+
+    /// Product of the cube counts of all colors in the bag.
+    fn power(&self) -> usize {
+        self.cubes.values().product()
+    }
+
+}
+
+impl fmt::Display for Named<'_, Bag> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut cubes : Vec<(&CubeColor, &usize)> = self.value.cubes.iter().collect();
+        cubes.sort();
+        let cubes : Vec<String> = cubes.iter().map(|(color, count)| format!("{}={}", self.colors.name(**color), count)).collect();
+        write!(f, "{}", cubes.join(","))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json
+}
 
-fn riddle_part_one(file_path: &String) {
+struct Options {
+    bag: String,
+    budget: Option<usize>,
+    format: OutputFormat,
+    generator: generator::GeneratorParams
+}
+
+impl Options {
+    fn from_args(args: &[String]) -> Self {
+        let mut bag = DEFAULT_BAG.to_string();
+        let mut budget = None;
+        let mut format = OutputFormat::Text;
+        let mut generator = generator::GeneratorParams::default();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bag" => {
+                    bag = args.next().expect("Missing value for --bag").clone();
+                }
+                "--bag-file" => {
+                    let bag_file = args.next().expect("Missing value for --bag-file");
+                    bag = fs::read_to_string(bag_file).expect("Error reading bag file");
+                }
+                "--format" => {
+                    format = match args.next().expect("Missing value for --format").as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        _ => panic!("Unknown format, expected text or json")
+                    };
+                }
+                "--budget" => {
+                    budget = Some(args.next().expect("Missing value for --budget").parse::<usize>().expect("Error parsing budget"));
+                }
+                "--games" => {
+                    generator.games = args.next().expect("Missing value for --games").parse::<usize>().expect("Error parsing game count");
+                }
+                "--draws" => {
+                    generator.draws = args.next().expect("Missing value for --draws").parse::<usize>().expect("Error parsing draw count");
+                }
+                "--colors" => {
+                    generator.colors = args.next().expect("Missing value for --colors").split(',').map(|c| c.trim().to_string()).collect();
+                }
+                "--feasible" => {
+                    generator.feasible = args.next().expect("Missing value for --feasible").parse::<f64>().expect("Error parsing feasible fraction");
+                }
+                "--seed" => {
+                    generator.seed = args.next().expect("Missing value for --seed").parse::<u64>().expect("Error parsing seed");
+                }
+                _ => panic!("Unknown option {:?}", arg)
+            }
+        }
+
+        Self {
+            bag,
+            budget,
+            format,
+            generator
+        }
+    }
+}
+
+fn riddle_part_one(file_path: &String, options: &Options) {
     let text = fs::read_to_string(file_path).expect("Error reading file");
     let lines : Vec<&str> = text.split('\n').collect();
 
 
-    let mut bag_loadout : HashMap<CubeColor, usize> = HashMap::new();
-    bag_loadout.insert(CubeColor::Red, 12);
-    bag_loadout.insert(CubeColor::Green, 13);
-    bag_loadout.insert(CubeColor::Blue, 14);
+    let mut colors = ColorSet::default();
+    let bag = Bag::from_string(&options.bag, &mut colors);
 
     let mut game_id_sum = 0;
 
     for line in lines {
-        let game = Game::from_string(line).expect("Error parsing game");
-        let mut is_possible = true;
-
-        'outer: for draw in game.cube_draws {
-            for (cube_color, cube_count) in draw.cubes {
-                if cube_count > bag_loadout[&cube_color] {
-                    is_possible = false;
-                    break 'outer;
-                }
-            }
-        }
+        let game = Game::from_string(line, &mut colors).expect("Error parsing game");
 
-        if is_possible {
+        if game.is_possible(&bag) {
             game_id_sum += game.id;
         }
         
@@ -111,56 +330,155 @@ fn riddle_part_one(file_path: &String) {
 
 }
 
+fn feasibility_report(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+    let lines : Vec<&str> = text.split('\n').collect();
+
+    let mut colors = ColorSet::default();
+    let bag = Bag::from_string(&options.bag, &mut colors);
+
+    let mut impossible_count = 0;
+
+    for line in lines {
+        let game = Game::from_string(line, &mut colors).expect("Error parsing game");
+
+        if let Feasibility::Impossible { draw_index, color, requested, available } = game.check(&bag) {
+            println!("Game {}: draw {} of {} needs {} {} but the bag holds {}",
+                game.id, draw_index + 1, game.cube_draws.len(), requested, colors.name(color), available);
+            impossible_count += 1;
+        }
+    }
+
+    println!("Impossible games: {:?}", impossible_count);
+}
+
 fn riddle_part_two(file_path: &String) {
     let text = fs::read_to_string(file_path).expect("Error reading file");
     let lines : Vec<&str> = text.split('\n').collect();
 
+    let mut colors = ColorSet::default();
     let mut cube_power_sum = 0;
 
     for line in lines {
-        let game = Game::from_string(line).expect("Error parsing game");
+        let game = Game::from_string(line, &mut colors).expect("Error parsing game");
+        cube_power_sum += game.minimal_bag().power();
+    }
 
-        let mut min_count_per_cube_color : HashMap<CubeColor, usize> = HashMap::new();
+    println!("Cube power sum: {:?}", cube_power_sum);
 
-        for draw in game.cube_draws {
-            for (cube_color, cube_count) in draw.cubes {
-               if cube_count > *min_count_per_cube_color.get(&cube_color).unwrap_or(&0) {
-                    min_count_per_cube_color.insert(cube_color, cube_count);
-               }
-            }
-        }
+}
 
-        let mut cube_power = 1;
-        for (cube_color, cube_count) in min_count_per_cube_color {
-            cube_power *= cube_count;
-        }
-        cube_power_sum += cube_power;
+fn optimize_report(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let mut colors = ColorSet::default();
+    let games = parse_games(&text, &mut colors);
+
+    let minimal_bag = optimize::minimal_bag_for_all(&games);
+    println!("Minimal bag for all {} games: {} ({} cubes)", games.len(), colors.show(&minimal_bag), minimal_bag.total());
+
+    let budget = match options.budget {
+        Some(budget) => budget,
+        None => return
+    };
+
+    let (most_games_bag, game_count) = optimize::best_bag_within_budget(&games, &colors, budget, |_| 1);
+    println!("Most possible games with {} cubes: {} games with {} ({} cubes)",
+        budget, game_count, colors.show(&most_games_bag), most_games_bag.total());
+
+    let (best_id_bag, id_sum) = optimize::best_bag_within_budget(&games, &colors, budget, |game| game.id);
+    println!("Highest game ID sum with {} cubes: {} with {} ({} cubes)",
+        budget, id_sum, colors.show(&best_id_bag), best_id_bag.total());
+}
+
+fn stats_report(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let mut colors = ColorSet::default();
+    let games = parse_games(&text, &mut colors);
+
+    print!("{}", stats::report(&games, &colors, options.format));
+    if options.format == OutputFormat::Json {
+        println!();
     }
+}
 
-    println!("Cube power sum: {:?}", cube_power_sum);
+/// Prints every game in the canonical `Game N: 3 blue, 4 red; ...` syntax.
+fn normalize(file_path: &String) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
 
+    let mut colors = ColorSet::default();
+    let games = parse_games(&text, &mut colors);
+
+    let lines : Vec<String> = games.iter().map(|game| colors.show(game).to_string()).collect();
+    print!("{}", lines.join("\n"));
+}
+
+/// Writes random games to the file and prints the expected answers.
+fn generate(file_path: &String, options: &Options) {
+    let mut colors = ColorSet::default();
+    let bag = Bag::from_string(&options.bag, &mut colors);
+
+    let (games, expected) = generator::generate(&options.generator, &mut colors, &bag);
+
+    let lines : Vec<String> = games.iter().map(|game| colors.show(game).to_string()).collect();
+    fs::write(file_path, lines.join("\n")).expect("Error writing file");
+
+    println!("Sum of all possible games: {:?}", expected.possible_id_sum);
+    println!("Cube power sum: {:?}", expected.cube_power_sum);
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
+    if args.len() < 3 {
         panic!("Provide the input text file!");
     }
-    let riddle_num: u32 = args
-        .get(1)
-        .unwrap()
-        .parse()
-        .expect("Error parsing riddle num");
+    let command = args.get(1).unwrap();
+    let options = Options::from_args(&args[3..]);
 
-    match riddle_num {
-        1 => {
-            riddle_part_one(args.get(2).unwrap());
+    match command.as_str() {
+        "1" => {
+            riddle_part_one(args.get(2).unwrap(), &options);
         }
-        2 => {
+        "2" => {
             riddle_part_two(args.get(2).unwrap());
         }
+        "feasibility" => {
+            feasibility_report(args.get(2).unwrap(), &options);
+        }
+        "optimize" => {
+            optimize_report(args.get(2).unwrap(), &options);
+        }
+        "normalize" => {
+            normalize(args.get(2).unwrap());
+        }
+        "generate" => {
+            generate(args.get(2).unwrap(), &options);
+        }
+        "stats" => {
+            stats_report(args.get(2).unwrap(), &options);
+        }
         _ => {
-            panic!("Unknown riddle part number");
+            panic!("Unknown riddle part number or command");
         }
     };
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_colors_in_a_draw_add_up() {
+        let mut colors = ColorSet::default();
+        let game = Game::from_string("Game 1: 3 red, 4 red; 5 red", &mut colors).unwrap();
+        let red = colors.get("red").unwrap();
+
+        assert_eq!(game.cube_draws[0].count(red), 7);
+        assert_eq!(game.minimal_bag().count(red), 7);
+
+        let bag = Bag::from_string("red=6", &mut colors);
+        assert_eq!(game.check(&bag), Feasibility::Impossible { draw_index: 0, color: red, requested: 7, available: 6 });
+        assert!(game.is_possible(&Bag::from_string("red=7", &mut colors)));
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use crate::{Bag, ColorSet, CubeColor, Game};
+
+/// The smallest bag that makes every game possible.
+pub fn minimal_bag_for_all(games: &[Game]) -> Bag {
+    let mut bag = Bag { cubes: HashMap::new() };
+    for game in games {
+        bag.cover(&game.minimal_bag());
+    }
+    bag
+}
+
+struct Search {
+    /// Cubes of every color each game needs, with the score of the game
+    requirements: Vec<(Vec<usize>, usize)>,
+    best: Option<(Vec<usize>, usize, usize)>
+}
+
+impl Search {
+    /// Cubes of the colors from `color_index` on that keep all `alive` games possible.
+    fn needed(&self, color_index: usize, alive: &[usize]) -> usize {
+        let color_count = self.requirements.first().map(|(counts, _)| counts.len()).unwrap_or(0);
+        (color_index..color_count).map(|color| {
+            alive.iter().map(|game_index| self.requirements[*game_index].0[color]).max().unwrap_or(0)
+        }).sum()
+    }
+
+    /// Highest score any subset of the `alive` games can reach. Keeping `k`
+    /// games takes at least the `k`th smallest requirement of every remaining
+    /// color, which limits how many games fit into the budget at all.
+    fn score_bound(&self, color_index: usize, alive: &[usize], budget: usize) -> usize {
+        let color_count = self.requirements.first().map(|(counts, _)| counts.len()).unwrap_or(0);
+        let sorted_counts : Vec<Vec<usize>> = (color_index..color_count).map(|color| {
+            let mut counts : Vec<usize> = alive.iter().map(|game_index| self.requirements[*game_index].0[color]).collect();
+            counts.sort_unstable();
+            counts
+        }).collect();
+
+        let max_games = (1..=alive.len()).take_while(|games| {
+            sorted_counts.iter().map(|counts| counts[games - 1]).sum::<usize>() <= budget
+        }).count();
+
+        let mut scores : Vec<usize> = alive.iter().map(|game_index| self.requirements[*game_index].1).collect();
+        scores.sort_unstable();
+        scores.iter().rev().take(max_games).sum()
+    }
+
+    /// Picks a cube count for every color in turn, keeping only the games that
+    /// still fit. Games needing more of the remaining colors than the budget
+    /// allows are dropped right away, and once all remaining games fit into the
+    /// budget together they are kept without branching any further. Branches
+    /// whose remaining games can not beat the best score found so far are
+    /// skipped.
+    fn descend(&mut self, color_index: usize, alive: Vec<usize>, budget: usize) {
+        let alive : Vec<usize> = alive.into_iter().filter(|game_index| {
+            self.requirements[*game_index].0[color_index..].iter().sum::<usize>() <= budget
+        }).collect();
+
+        if let Some((_, best_score, _)) = self.best {
+            if self.score_bound(color_index, &alive, budget) < best_score {
+                return;
+            }
+        }
+
+        if self.needed(color_index, &alive) <= budget {
+            let alive_score : usize = alive.iter().map(|game_index| self.requirements[*game_index].1).sum();
+            let cost = self.needed(0, &alive);
+
+            let is_better = match self.best {
+                Some((_, best_score, best_cost)) => alive_score > best_score || (alive_score == best_score && cost < best_cost),
+                None => true
+            };
+            if is_better {
+                self.best = Some((alive, alive_score, cost));
+            }
+            return;
+        }
+
+        let mut candidates : Vec<usize> = alive.iter().map(|game_index| self.requirements[*game_index].0[color_index]).collect();
+        candidates.push(0);
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        for count in candidates.into_iter().rev().filter(|count| *count <= budget) {
+            let kept : Vec<usize> = alive.iter().copied().filter(|game_index| self.requirements[*game_index].0[color_index] <= count).collect();
+            self.descend(color_index + 1, kept, budget - count);
+        }
+    }
+}
+
+/// Finds the bag with at most `budget` cubes that maximizes the summed `score`
+/// of all games it makes possible. Ties are broken by using fewer cubes.
+///
+/// Pruning keeps typical inputs fast, but the search remains exponential in
+/// the number of colors in the worst case.
+///
+/// Scoring every game with `1` gives the smallest bag that makes the largest
+/// number of games possible, scoring with the game ID maximizes the part one answer.
+pub fn best_bag_within_budget(games: &[Game], colors: &ColorSet, budget: usize, score: impl Fn(&Game) -> usize) -> (Bag, usize) {
+    let colors : Vec<CubeColor> = colors.iter().collect();
+    let mut search = Search {
+        requirements: games.iter().map(|game| {
+            let minimal_bag = game.minimal_bag();
+            (colors.iter().map(|color| minimal_bag.count(*color)).collect(), score(game))
+        }).collect(),
+        best: None
+    };
+    search.descend(0, (0..games.len()).collect(), budget);
+
+    let (kept, best_score, _) = search.best.expect("The empty bag is always a candidate");
+
+    let mut bag = Bag { cubes: HashMap::new() };
+    for game_index in kept {
+        bag.cover(&games[game_index].minimal_bag());
+    }
+    (bag, best_score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{self, GeneratorParams};
+    use crate::parse_games;
+
+    /// Best score and fewest cubes over every subset of the games.
+    fn brute_force(games: &[Game], budget: usize, score: impl Fn(&Game) -> usize) -> (usize, usize) {
+        let mut best = (0, 0);
+        for subset in 0..1usize << games.len() {
+            let mut bag = Bag { cubes: HashMap::new() };
+            let mut subset_score = 0;
+            for (_, game) in games.iter().enumerate().filter(|(game_index, _)| subset & (1 << game_index) != 0) {
+                bag.cover(&game.minimal_bag());
+                subset_score += score(game);
+            }
+            if bag.total() <= budget && (subset_score > best.0 || (subset_score == best.0 && bag.total() < best.1)) {
+                best = (subset_score, bag.total());
+            }
+        }
+        best
+    }
+
+    fn assert_matches_brute_force(games: &[Game], colors: &ColorSet, budget: usize) {
+        let (bag, game_count) = best_bag_within_budget(games, colors, budget, |_| 1);
+        assert_eq!((game_count, bag.total()), brute_force(games, budget, |_| 1), "budget {}", budget);
+        assert_eq!(games.iter().filter(|game| game.is_possible(&bag)).count(), game_count);
+
+        let (bag, id_sum) = best_bag_within_budget(games, colors, budget, |game| game.id);
+        assert_eq!((id_sum, bag.total()), brute_force(games, budget, |game| game.id), "budget {}", budget);
+    }
+
+    #[test]
+    fn best_bag_on_sample() {
+        let mut colors = ColorSet::default();
+        let games = parse_games(include_str!("sample.txt"), &mut colors);
+
+        // Games 1, 2, 4 and 5 need 14 red, 3 green and 15 blue cubes
+        let (bag, id_sum) = best_bag_within_budget(&games, &colors, 32, |game| game.id);
+        assert_eq!((id_sum, bag.total()), (12, 32));
+        // Without game 4, games 1, 2 and 5 fit into 15 cubes
+        let (bag, id_sum) = best_bag_within_budget(&games, &colors, 31, |game| game.id);
+        assert_eq!((id_sum, bag.total()), (8, 15));
+
+        for budget in 0..=50 {
+            assert_matches_brute_force(&games, &colors, budget);
+        }
+    }
+
+    #[test]
+    fn best_bag_with_many_colors() {
+        let params = GeneratorParams {
+            games: 10,
+            colors: ["a", "b", "c", "d", "e", "f"].iter().map(|name| name.to_string()).collect(),
+            seed: 3,
+            ..GeneratorParams::default()
+        };
+        let mut colors = ColorSet::default();
+        let bag = Bag::from_string("a=5,b=6,c=7,d=8,e=9,f=10", &mut colors);
+        let (games, _) = generator::generate(&params, &mut colors, &bag);
+
+        for budget in (0..=80).step_by(5) {
+            assert_matches_brute_force(&games, &colors, budget);
+        }
+    }
+}
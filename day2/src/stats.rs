@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::{Bag, ColorSet, CubeColor, CubeDraw, Game, OutputFormat};
+
+/// Only bags with at most this many times the largest observed draw of a
+/// color are considered when estimating the bag contents.
+const LIKELY_BAG_SEARCH_FACTOR : usize = 3;
+
+/// Distribution of the number of cubes of one color per draw.
+/// Draws that do not mention the color count as zero cubes.
+#[derive(Debug, Default)]
+pub struct ColorStats {
+    pub mean: f64,
+    pub max: usize,
+    pub variance: f64,
+    pub histogram: BTreeMap<usize, usize>
+}
+
+impl ColorStats {
+    pub fn from_draws(draws: &[&CubeDraw], color: CubeColor) -> Self {
+        if draws.is_empty() {
+            return Self::default();
+        }
+
+        let counts : Vec<usize> = draws.iter().map(|draw| draw.count(color)).collect();
+        let mean = counts.iter().sum::<usize>() as f64 / counts.len() as f64;
+        let variance = counts.iter().map(|count| (*count as f64 - mean).powi(2)).sum::<f64>() / counts.len() as f64;
+
+        let mut histogram : BTreeMap<usize, usize> = BTreeMap::new();
+        for count in &counts {
+            *histogram.entry(*count).or_insert(0) += 1;
+        }
+
+        Self {
+            mean,
+            max: *counts.iter().max().unwrap(),
+            variance,
+            histogram
+        }
+    }
+}
+
+/// `text` as a quoted JSON string. Color names can be any token without
+/// whitespace, so quotes, backslashes and control characters are escaped.
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Natural logarithms of `0!..=n!`.
+fn ln_factorials(n: usize) -> Vec<f64> {
+    let mut table = vec![0.0; n + 1];
+    for i in 1..=n {
+        table[i] = table[i - 1] + (i as f64).ln();
+    }
+    table
+}
+
+fn ln_binomial(ln_fact: &[f64], n: usize, k: usize) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    ln_fact[n] - ln_fact[k] - ln_fact[n - k]
+}
+
+/// Log likelihood of seeing all draws, each one taken without replacement
+/// from the bag and put back afterwards (multivariate hypergeometric).
+fn ln_likelihood(ln_fact: &[f64], draws: &[&CubeDraw], colors: &[CubeColor], bag: &[usize]) -> f64 {
+    let bag_total : usize = bag.iter().sum();
+
+    draws.iter().map(|draw| {
+        let draw_total : usize = colors.iter().map(|color| draw.count(*color)).sum();
+        let per_color : f64 = colors.iter().zip(bag).map(|(color, count)| ln_binomial(ln_fact, *count, draw.count(*color))).sum();
+        per_color - ln_binomial(ln_fact, bag_total, draw_total)
+    }).sum()
+}
+
+/// Likely bag contents given the draws, a heuristic for the maximum
+/// likelihood estimate.
+///
+/// The search starts at the minimal bag and moves one color at a time to
+/// whichever count improves the likelihood the most, until nothing changes.
+/// This can stop at a local maximum, and only counts up to
+/// `LIKELY_BAG_SEARCH_FACTOR` times the largest draw of each color are tried.
+pub fn likely_bag(draws: &[&CubeDraw], colors: &[CubeColor]) -> Bag {
+    let lower : Vec<usize> = colors.iter().map(|color| draws.iter().map(|draw| draw.count(*color)).max().unwrap_or(0)).collect();
+    let upper : Vec<usize> = lower.iter().map(|count| count * LIKELY_BAG_SEARCH_FACTOR).collect();
+    let ln_fact = ln_factorials(upper.iter().sum());
+
+    let mut bag = lower.clone();
+    let mut best = ln_likelihood(&ln_fact, draws, colors, &bag);
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for color_index in 0..colors.len() {
+            for count in lower[color_index]..=upper[color_index] {
+                let mut candidate = bag.clone();
+                candidate[color_index] = count;
+
+                let likelihood = ln_likelihood(&ln_fact, draws, colors, &candidate);
+                if likelihood > best + f64::EPSILON {
+                    best = likelihood;
+                    bag = candidate;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let cubes : HashMap<CubeColor, usize> = colors.iter().copied().zip(bag).filter(|(_, count)| *count > 0).collect();
+    Bag {
+        cubes
+    }
+}
+
+struct DrawSummary {
+    label: String,
+    stats: Vec<(CubeColor, ColorStats)>,
+    likely_bag: Bag
+}
+
+impl DrawSummary {
+    fn new(label: String, draws: &[&CubeDraw], colors: &[CubeColor]) -> Self {
+        Self {
+            label,
+            stats: colors.iter().map(|color| (*color, ColorStats::from_draws(draws, *color))).collect(),
+            likely_bag: likely_bag(draws, colors)
+        }
+    }
+
+    fn to_text(&self, colors: &ColorSet) -> String {
+        let mut text = format!("{}\n", self.label);
+        for (color, stats) in &self.stats {
+            text.push_str(&format!("  {}: mean {:.2}, max {}, variance {:.2}\n", colors.name(*color), stats.mean, stats.max, stats.variance));
+        }
+        text.push_str(&format!("  likely bag: {}\n", colors.show(&self.likely_bag)));
+        text
+    }
+
+    fn to_json(&self, colors: &ColorSet) -> String {
+        let stats : Vec<String> = self.stats.iter().map(|(color, stats)| {
+            format!("{}:{{\"mean\":{},\"max\":{},\"variance\":{}}}", json_string(colors.name(*color)), stats.mean, stats.max, stats.variance)
+        }).collect();
+        let likely_bag : Vec<String> = colors.iter().map(|color| format!("{}:{}", json_string(colors.name(color)), self.likely_bag.count(color))).collect();
+
+        format!("{{\"label\":{},\"colors\":{{{}}},\"likely_bag\":{{{}}}}}", json_string(&self.label), stats.join(","), likely_bag.join(","))
+    }
+}
+
+fn histogram_to_text(stats: &[(CubeColor, ColorStats)], colors: &ColorSet) -> String {
+    let mut text = String::from("Histogram of cubes per draw\n");
+    for (color, stats) in stats {
+        text.push_str(&format!("  {}\n", colors.name(*color)));
+        for (count, frequency) in &stats.histogram {
+            text.push_str(&format!("  {:>4} | {} ({})\n", count, "#".repeat(*frequency), frequency));
+        }
+    }
+    text
+}
+
+fn histogram_to_json(stats: &[(CubeColor, ColorStats)], colors: &ColorSet) -> String {
+    let per_color : Vec<String> = stats.iter().map(|(color, stats)| {
+        let buckets : Vec<String> = stats.histogram.iter().map(|(count, frequency)| format!("\"{}\":{}", count, frequency)).collect();
+        format!("{}:{{{}}}", json_string(colors.name(*color)), buckets.join(","))
+    }).collect();
+    format!("{{{}}}", per_color.join(","))
+}
+
+/// Statistics for every game on its own and for all draws across all games.
+pub fn report(games: &[Game], colors: &ColorSet, format: OutputFormat) -> String {
+    let color_list : Vec<CubeColor> = colors.iter().collect();
+
+    let per_game : Vec<DrawSummary> = games.iter().map(|game| {
+        let draws : Vec<&CubeDraw> = game.cube_draws.iter().collect();
+        DrawSummary::new(format!("Game {}", game.id), &draws, &color_list)
+    }).collect();
+
+    let all_draws : Vec<&CubeDraw> = games.iter().flat_map(|game| game.cube_draws.iter()).collect();
+    let overall = DrawSummary::new(String::from("All games"), &all_draws, &color_list);
+
+    match format {
+        OutputFormat::Text => {
+            let mut text : String = per_game.iter().map(|summary| summary.to_text(colors)).collect();
+            text.push_str(&overall.to_text(colors));
+            text.push_str(&histogram_to_text(&overall.stats, colors));
+            text
+        }
+        OutputFormat::Json => {
+            let games_json : Vec<String> = per_game.iter().map(|summary| summary.to_json(colors)).collect();
+            format!("{{\"games\":[{}],\"all\":{},\"histogram\":{}}}",
+                games_json.join(","), overall.to_json(colors), histogram_to_json(&overall.stats, colors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_games;
+
+    #[test]
+    fn escapes_color_names_in_json() {
+        let mut colors = ColorSet::default();
+        let games = parse_games("Game 1: 3 a\"b, 2 c\\d", &mut colors);
+        let json = report(&games, &colors, OutputFormat::Json);
+
+        assert!(json.contains(r#""a\"b":{"mean":3"#));
+        assert!(json.contains(r#""c\\d":{"mean":2"#));
+        assert!(!json.contains(r#""a"b""#));
+    }
+}
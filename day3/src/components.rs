@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::{Cell, Coord2D, EngineSchematic};
+
+/// Parts and symbols that are connected to each other through adjacency.
+#[derive(Debug)]
+pub struct Machine {
+    /// IDs of the parts
+    pub parts: Vec<usize>,
+    pub symbols: Vec<Coord2D>,
+    pub total: i64
+}
+
+#[derive(Debug)]
+pub struct ComponentAnalysis {
+    /// Components with at least one part and one symbol
+    pub machines: Vec<Machine>,
+    /// Parts that do not touch any symbol, directly or through other parts
+    pub isolated_parts: Vec<usize>,
+    /// Symbols that do not touch any part, directly or through other symbols
+    pub lonely_symbols: Vec<Coord2D>
+}
+
+impl ComponentAnalysis {
+    /// The machine with the highest total part value.
+    pub fn largest_machine(&self) -> Option<&Machine> {
+        self.machines.iter().max_by_key(|machine| machine.total)
+    }
+}
+
+struct DisjointSet {
+    parents: Vec<usize>
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect()
+        }
+    }
+
+    fn find(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        let mut node = node;
+        while self.parents[node] != root {
+            let next = self.parents[node];
+            self.parents[node] = root;
+            node = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let root_a = self.find(a);
+        let root_b = self.find(b);
+        if root_a != root_b {
+            self.parents[root_b] = root_a;
+        }
+    }
+}
+
+/// Treats parts and symbols as nodes of a graph, with an edge between any two
+/// of them that touch (parts next to parts and symbols next to symbols included),
+/// and splits the graph into connected components.
+pub fn analyze(schematic: &EngineSchematic) -> ComponentAnalysis {
+    let part_count = schematic.parts.len();
+    let symbol_ids : HashMap<&Coord2D, usize> = schematic.symbols.iter().enumerate().map(|(index, coord)| (coord, part_count + index)).collect();
+
+    let node_at = |coord: &Coord2D| match schematic.cell(coord) {
+        Cell::Part(part_id) => Some(part_id),
+        Cell::Symbol(_) => symbol_ids.get(coord).copied(),
+        Cell::Empty => None
+    };
+
+    let mut nodes = DisjointSet::new(part_count + schematic.symbols.len());
+
+    let part_coords = schematic.parts.iter().flat_map(|part| part.digit_coords.iter());
+    for coord in part_coords.chain(schematic.symbols.iter()) {
+        let node = node_at(coord).unwrap();
+        for potential_pos in schematic.neighbours(coord) {
+            if let Some(neighbour) = node_at(&potential_pos) {
+                nodes.union(node, neighbour);
+            }
+        }
+    }
+
+    let mut components : HashMap<usize, (Vec<usize>, Vec<Coord2D>)> = HashMap::new();
+    for part_id in 0..part_count {
+        components.entry(nodes.find(part_id)).or_default().0.push(part_id);
+    }
+    for (index, coord) in schematic.symbols.iter().enumerate() {
+        components.entry(nodes.find(part_count + index)).or_default().1.push(coord.clone());
+    }
+
+    let mut components : Vec<(Vec<usize>, Vec<Coord2D>)> = components.into_values().collect();
+    components.sort_by_key(|(parts, symbols)| (parts.first().copied(), symbols.first().map(|coord| (coord.y, coord.x))));
+
+    let mut analysis = ComponentAnalysis {
+        machines: Vec::new(),
+        isolated_parts: Vec::new(),
+        lonely_symbols: Vec::new()
+    };
+
+    for (parts, symbols) in components {
+        if symbols.is_empty() {
+            analysis.isolated_parts.extend(parts);
+        } else if parts.is_empty() {
+            analysis.lonely_symbols.extend(symbols);
+        } else {
+            let total = parts.iter().map(|part_id| schematic.parts[*part_id].number).sum();
+            analysis.machines.push(Machine {
+                parts,
+                symbols,
+                total
+            });
+        }
+    }
+    analysis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SymbolFilter, TokenizerMode};
+
+    fn schematic(text: &str) -> EngineSchematic {
+        EngineSchematic::from_string(text, &SymbolFilter::Inferred { exclude: Vec::new() }, &TokenizerMode::default()).unwrap()
+    }
+
+    fn numbers(schematic: &EngineSchematic, part_ids: &[usize]) -> Vec<i64> {
+        part_ids.iter().map(|part_id| schematic.parts[*part_id].number).collect()
+    }
+
+    #[test]
+    fn finds_machines_in_sample() {
+        let schematic = schematic(include_str!("sample.txt"));
+        let analysis = analyze(&schematic);
+
+        assert_eq!(analysis.machines.len(), 6);
+        let largest = analysis.largest_machine().unwrap();
+        assert_eq!(largest.total, 1353);
+        assert_eq!(numbers(&schematic, &largest.parts), vec![755, 598]);
+        assert_eq!(numbers(&schematic, &analysis.isolated_parts), vec![114, 58]);
+        assert!(analysis.lonely_symbols.is_empty());
+    }
+
+    #[test]
+    fn joins_chains_and_finds_lonely_symbols() {
+        // 12 and 3 only reach the `*` through 45, the `#` and `%` touch
+        // each other but no part, and 6 touches nothing at all.
+        let schematic = schematic("12.....#\n..45...%\n...*3...\n6.......");
+        let analysis = analyze(&schematic);
+
+        assert_eq!(analysis.machines.len(), 1);
+        assert_eq!(numbers(&schematic, &analysis.machines[0].parts), vec![12, 45, 3]);
+        assert_eq!(analysis.machines[0].total, 60);
+        assert_eq!(numbers(&schematic, &analysis.isolated_parts), vec![6]);
+        assert_eq!(analysis.lonely_symbols, vec![Coord2D { x: 7, y: 0 }, Coord2D { x: 7, y: 1 }]);
+    }
+}
@@ -1,8 +1,11 @@
-use std::collections::HashSet;
 use std::fs;
 use std::env;
+use std::fmt;
+use std::io;
 
-const SYMBOLS : [char; 11] = ['%', '+', '#', '&', '$', '*', '\n', '=', '-', '@', '/'];
+mod components;
+mod render;
+mod streaming;
 
 const OFFSETS : [Coord2D; 8] = [
     Coord2D {x: -1, y: -1}, Coord2D {x: 0, y: -1}, Coord2D {x: 1, y: -1},
@@ -10,16 +13,93 @@ const OFFSETS : [Coord2D; 8] = [
     Coord2D {x: -1, y: 1}, Coord2D {x: 0, y: 1}, Coord2D {x: 1, y: 1},
 ];
 
+const VON_NEUMANN_OFFSETS : [Coord2D; 4] = [
+    Coord2D {x: 0, y: -1}, Coord2D {x: -1, y: 0}, Coord2D {x: 1, y: 0}, Coord2D {x: 0, y: 1},
+];
+
+/// What happens to neighbours that fall outside of the schematic.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum BoundaryMode {
+    /// They do not exist
+    #[default]
+    Clipped,
+    /// They wrap around to the opposite edge (toroidal)
+    Wrap
+}
+
+/// Which cells count as touching a cell.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum Adjacency {
+    /// All 8 surrounding cells, see `OFFSETS`
+    #[default]
+    Moore,
+    /// Only the 4 cells sharing an edge
+    VonNeumann
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Neighbourhood {
+    boundary: BoundaryMode,
+    adjacency: Adjacency
+}
+
 #[derive(Hash, PartialEq, Debug, Eq, Clone)]
 struct Coord2D {
     x: isize,
     y: isize
 }
 
+/// Decides which characters of the schematic are symbols.
+#[derive(Debug, Clone, PartialEq)]
+enum SymbolFilter {
+    /// Anything that is not a digit, `.` or whitespace, except the listed characters
+    Inferred { exclude: Vec<char> },
+    /// Only the listed characters
+    Only(Vec<char>)
+}
+
+impl SymbolFilter {
+    fn is_symbol(&self, c: char) -> bool {
+        match self {
+            SymbolFilter::Inferred { exclude } => !c.is_ascii_digit() && c != '.' && !c.is_whitespace() && !exclude.contains(&c),
+            SymbolFilter::Only(symbols) => symbols.contains(&c)
+        }
+    }
+}
+
+/// Values that do not fit into the `i64` part numbers.
+#[derive(Debug, PartialEq)]
+enum SchematicError {
+    /// The number starting at `coord` does not fit into an `i64`
+    PartNumberTooLarge { coord: Coord2D },
+    /// Combining the parts around the gear at `coord` overflows
+    GearOverflow { coord: Coord2D, symbol: char },
+    /// The sum of all gear values overflows
+    GearRatioOverflow
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchematicError::PartNumberTooLarge { coord } => write!(f, "The part number at ({}, {}) is too large", coord.x, coord.y),
+            SchematicError::GearOverflow { coord, symbol } => write!(f, "The value of gear {} at ({}, {}) overflows", symbol, coord.x, coord.y),
+            SchematicError::GearRatioOverflow => write!(f, "The sum of all gear values overflows")
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq,)]
 struct EnginePart {
-    number: u32,
-    digit_coords: HashSet<Coord2D>
+    number: i64,
+    digit_coords: Vec<Coord2D>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Empty,
+    /// Index into `EngineSchematic::parts`
+    Part(usize),
+    Symbol(char)
 }
 
 #[derive(Debug)]
@@ -28,164 +108,617 @@ struct EngineSchematic {
     height: usize,
 
     parts: Vec<EnginePart>,
-    symbols: HashSet<Coord2D>
+    symbols: Vec<Coord2D>,
+    /// Row major, `width * height` cells
+    cells: Vec<Cell>,
+    neighbourhood: Neighbourhood
+}
+
+/// How numbers are read from the schematic.
+///
+/// By default a number is a run of digits within a single row and `-` is
+/// always a symbol. With `signed`, a `-` directly in front of a digit becomes
+/// the sign of that number instead, unless it directly follows another digit
+/// (`5-3` stays `5`, `-`, `3`). With `wrap_lines`, a number that reaches the
+/// end of a row continues with the digits at the start of the next row.
+///
+/// Part numbers are `i64`, so a number may have at most 18 digits. Longer
+/// runs, which are easy to get with `wrap_lines`, are reported as errors.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct TokenizerMode {
+    signed: bool,
+    wrap_lines: bool
+}
+
+fn flush_number(number_buffer: &mut String, digit_coords: &mut Vec<Coord2D>, engine_part_list: &mut Vec<EnginePart>) -> Result<(), SchematicError> {
+    if number_buffer.is_empty() {
+        return Ok(());
+    }
+
+    let number = number_buffer.parse::<i64>().map_err(|_| SchematicError::PartNumberTooLarge { coord: digit_coords[0].clone() })?;
+    engine_part_list.push(EnginePart {
+        number,
+        digit_coords: digit_coords.clone()
+    });
+    number_buffer.clear();
+    digit_coords.clear();
+    Ok(())
 }
 
 impl EngineSchematic {
 
-    fn from_string(text: &str, symbols_chars: &[char]) -> Self {
-        let rows : Vec<&str> = text.split('\n').collect();
+    fn from_string(text: &str, symbol_filter: &SymbolFilter, mode: &TokenizerMode) -> Result<Self, SchematicError> {
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let rows : Vec<Vec<char>> = text.split('\n').map(|row| row.chars().collect()).collect();
 
-        let width = rows[0].len();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
         let height = rows.len();
-    
+
+        // Every character with its position, `None` marks the end of a row
+        // unless numbers may continue on the next one.
+        let mut stream : Vec<Option<(char, Coord2D)>> = Vec::new();
+        for (row_index, row) in rows.iter().enumerate() {
+            for (col_index, c) in row.iter().enumerate() {
+                stream.push(Some((*c, Coord2D { x: col_index as isize, y: row_index as isize })));
+            }
+            if !mode.wrap_lines {
+                stream.push(None);
+            }
+        }
+
         let mut engine_part_list : Vec<EnginePart> = Vec::new();
-        let mut symbol_list : HashSet<Coord2D> = HashSet::new();
-    
-        for (row_index, row_text) in rows.iter().enumerate() {
-    
-            let digit_indicies : Vec<(usize, &str)> = row_text.match_indices(|c: char| c.is_digit(10)).collect();
-            
-            let mut number_buffer = String::new();
-            let mut digit_coords : HashSet<Coord2D> = HashSet::new();
-            let mut last_digit_pos : Option<usize> = None;
-    
-            for (digit_pos, digit_slc) in digit_indicies {
-                if last_digit_pos == None || last_digit_pos.unwrap() + 1 == digit_pos  {
-                    number_buffer.push_str(digit_slc);
-                    last_digit_pos = Some(digit_pos);
-                } else {                
-                    let engine_part = EnginePart {
-                        number: number_buffer.parse::<u32>().unwrap(),
-                        digit_coords: digit_coords.clone()
-                    };
-                    engine_part_list.push(engine_part);
-    
-                    digit_coords.clear();
-                    number_buffer = digit_slc.to_string();
-                    last_digit_pos = Some(digit_pos)
+        let mut symbol_list : Vec<(Coord2D, char)> = Vec::new();
+
+        let mut number_buffer = String::new();
+        let mut digit_coords : Vec<Coord2D> = Vec::new();
+
+        for (index, token) in stream.iter().enumerate() {
+            let (c, coord) = match token {
+                Some((c, coord)) => (*c, coord),
+                None => {
+                    flush_number(&mut number_buffer, &mut digit_coords, &mut engine_part_list)?;
+                    continue;
                 }
-                digit_coords.insert(Coord2D { x: digit_pos as isize, y: row_index as isize});
-    
+            };
+
+            let starts_signed_number = mode.signed && c == '-' && number_buffer.is_empty()
+                && matches!(stream.get(index + 1), Some(Some((next, _))) if next.is_ascii_digit());
+
+            if c.is_ascii_digit() || starts_signed_number {
+                number_buffer.push(c);
+                digit_coords.push(coord.clone());
+                continue;
             }
-    
-            if number_buffer.len() > 0 {
-                let engine_part = EnginePart {
-                    number: number_buffer.parse::<u32>().unwrap(),
-                    digit_coords: digit_coords.clone()
-                };
-                engine_part_list.push(engine_part);
+
+            flush_number(&mut number_buffer, &mut digit_coords, &mut engine_part_list)?;
+            if symbol_filter.is_symbol(c) {
+                symbol_list.push((coord.clone(), c));
             }
-    
+        }
+        flush_number(&mut number_buffer, &mut digit_coords, &mut engine_part_list)?;
 
-            let symbol_coords : HashSet<Coord2D> = row_text.match_indices(|c: char| symbols_chars.contains(&c)).map(|(pos, _s)| Coord2D {x: pos as isize, y:row_index as isize}).collect();
-            for cr in symbol_coords {
-                symbol_list.insert(cr);
+        let mut cells = vec![Cell::Empty; width * height];
+        for (part_id, part) in engine_part_list.iter().enumerate() {
+            for digit_coord in &part.digit_coords {
+                cells[digit_coord.y as usize * width + digit_coord.x as usize] = Cell::Part(part_id);
             }
-        }   
+        }
+        for (symbol_coord, symbol) in &symbol_list {
+            cells[symbol_coord.y as usize * width + symbol_coord.x as usize] = Cell::Symbol(*symbol);
+        }
     
-        Self {
+        Ok(Self {
             width,
             height,
             parts: engine_part_list,
-            symbols: symbol_list
-        }
+            symbols: symbol_list.into_iter().map(|(coord, _)| coord).collect(),
+            cells,
+            neighbourhood: Neighbourhood::default()
+        })
+    }
+
+    fn with_neighbourhood(mut self, neighbourhood: Neighbourhood) -> Self {
+        self.neighbourhood = neighbourhood;
+        self
+    }
+
+    /// Distinct symbol characters found in the schematic, sorted.
+    fn symbol_chars(&self) -> Vec<char> {
+        let mut symbol_chars : Vec<char> = self.symbols.iter().filter_map(|coord| match self.cell(coord) {
+            Cell::Symbol(symbol) => Some(symbol),
+            _ => None
+        }).collect();
+        symbol_chars.sort();
+        symbol_chars.dedup();
+        symbol_chars
     }
 
+    /// Cell at the coordinate, anything outside of the schematic is empty.
+    fn cell(&self, coord: &Coord2D) -> Cell {
+        if coord.x < 0 || coord.y < 0 || coord.x as usize >= self.width || coord.y as usize >= self.height {
+            return Cell::Empty;
+        }
+        self.cells[coord.y as usize * self.width + coord.x as usize]
+    }
 
-    fn get_valid_engine_parts_sum(&self) -> u32 {
+    /// Coordinates touching the given one, according to the neighbourhood of the schematic.
+    fn neighbours(&self, coord: &Coord2D) -> Vec<Coord2D> {
+        let offsets : &[Coord2D] = match self.neighbourhood.adjacency {
+            Adjacency::Moore => &OFFSETS,
+            Adjacency::VonNeumann => &VON_NEUMANN_OFFSETS
+        };
+
+        offsets.iter().map(|offs| Coord2D { x: coord.x + offs.x, y: coord.y + offs.y }).filter_map(|pos| {
+            match self.neighbourhood.boundary {
+                BoundaryMode::Clipped => {
+                    let inside = pos.x >= 0 && pos.y >= 0 && (pos.x as usize) < self.width && (pos.y as usize) < self.height;
+                    inside.then_some(pos)
+                }
+                BoundaryMode::Wrap => Some(Coord2D {
+                    x: pos.x.rem_euclid(self.width as isize),
+                    y: pos.y.rem_euclid(self.height as isize)
+                })
+            }
+        }).collect()
+    }
 
-      
-        let mut part_sum = 0;
+    /// IDs of all distinct parts touching the coordinate.
+    fn neighbouring_parts(&self, coord: &Coord2D) -> Vec<usize> {
+        let mut part_ids : Vec<usize> = Vec::new();
 
-        for engine_part in &self.parts {
+        for potential_pos in self.neighbours(coord) {
+            if let Cell::Part(part_id) = self.cell(&potential_pos) {
+                if !part_ids.contains(&part_id) {
+                    part_ids.push(part_id);
+                }
+            }
+        }
+        part_ids
+    }
 
-            'loop_digits: for digit_coords in &engine_part.digit_coords {
 
-                for offs in &OFFSETS {
-                    let potential_symbol_pos = Coord2D { x: digit_coords.x + offs.x, y: digit_coords.y + offs.y };
-                    
-                    if self.symbols.contains(&potential_symbol_pos) {
-                        part_sum += engine_part.number;
-                        break 'loop_digits;
-                    }
+    /// For every part whether it touches any symbol.
+    fn valid_parts(&self) -> Vec<bool> {
+        let mut is_valid = vec![false; self.parts.len()];
+        for symbol in &self.symbols {
+            for part_id in self.neighbouring_parts(symbol) {
+                is_valid[part_id] = true;
+            }
+        }
+        is_valid
+    }
 
+    /// The first symbol found next to any digit of the part.
+    fn validating_symbol(&self, part_id: usize) -> Option<(Coord2D, char)> {
+        for digit_coord in &self.parts[part_id].digit_coords {
+            for potential_pos in self.neighbours(digit_coord) {
+                if let Cell::Symbol(symbol) = self.cell(&potential_pos) {
+                    return Some((potential_pos, symbol));
                 }
+            }
+        }
+        None
+    }
 
+    fn get_valid_engine_parts_sum(&self) -> i64 {
+        self.parts.iter().zip(self.valid_parts()).filter(|(_, valid)| *valid).map(|(part, _)| part.number).sum()
+    }
+
+
+    /// All symbols that satisfy the gear rule, together with their neighbouring parts.
+    fn find_gears(&self, rule: &GearRule) -> Result<Vec<Gear>, SchematicError> {
+        let mut gears : Vec<Gear> = Vec::new();
+
+        for symbol_coord in &self.symbols {
+            let symbol = match self.cell(symbol_coord) {
+                Cell::Symbol(symbol) if rule.symbols.contains(&symbol) => symbol,
+                _ => continue
+            };
+
+            let neighbours = self.neighbouring_parts(symbol_coord);
+            if !rule.neighbours.allows(neighbours.len()) {
+                continue;
             }
 
+            let mut numbers = neighbours.iter().map(|part_id| self.parts[*part_id].number);
+            let value = match rule.combiner {
+                Combiner::Product => numbers.try_fold(1i64, |product, number| product.checked_mul(number)),
+                Combiner::Sum => numbers.try_fold(0i64, |sum, number| sum.checked_add(number)),
+                Combiner::Max => Some(numbers.max().unwrap_or(0))
+            }.ok_or(SchematicError::GearOverflow { coord: symbol_coord.clone(), symbol })?;
+
+            gears.push(Gear {
+                coord: symbol_coord.clone(),
+                symbol,
+                neighbours,
+                value
+            });
+        }
+        Ok(gears)
+    }
+
+    fn get_gear_ratio(&self, rule: &GearRule) -> Result<i64, SchematicError> {
+        self.find_gears(rule)?.iter().try_fold(0i64, |sum, gear| sum.checked_add(gear.value)).ok_or(SchematicError::GearRatioOverflow)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NeighbourCount {
+    Exactly(usize),
+    AtLeast(usize),
+    /// Inclusive on both ends
+    Between(usize, usize)
+}
+
+impl NeighbourCount {
+    /// Parses `2`, `2+` or `2-4`.
+    fn from_string(text: &str) -> Self {
+        let parse = |t: &str| t.trim().parse::<usize>().expect("Error parsing neighbour count");
+
+        if let Some(min) = text.strip_suffix('+') {
+            NeighbourCount::AtLeast(parse(min))
+        } else if let Some((min, max)) = text.split_once('-') {
+            NeighbourCount::Between(parse(min), parse(max))
+        } else {
+            NeighbourCount::Exactly(parse(text))
         }
-        part_sum
     }
 
+    fn allows(&self, count: usize) -> bool {
+        match *self {
+            NeighbourCount::Exactly(n) => count == n,
+            NeighbourCount::AtLeast(n) => count >= n,
+            NeighbourCount::Between(min, max) => (min..=max).contains(&count)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combiner {
+    Product,
+    Sum,
+    Max
+}
 
-    fn get_gear_ratio(&self) -> u32 {
-        // at this point symbols only contains the gears
+impl Combiner {
+    fn from_string(text: &str) -> Self {
+        match text {
+            "product" => Combiner::Product,
+            "sum" => Combiner::Sum,
+            "max" => Combiner::Max,
+            _ => panic!("Unknown combiner, expected product, sum or max")
+        }
+    }
+}
 
-        let mut gear_ratio = 0;
+/// Which symbols count as gears and how their neighbouring parts are combined.
+/// The default is the riddle rule: `*` with exactly two parts, multiplied.
+#[derive(Debug, Clone)]
+struct GearRule {
+    symbols: Vec<char>,
+    neighbours: NeighbourCount,
+    combiner: Combiner
+}
 
-        for gear in &self.symbols {
-            let mut neighbouring_engine_parts : Vec<&EnginePart> = Vec::new();
+impl Default for GearRule {
+    fn default() -> Self {
+        Self {
+            symbols: vec!['*'],
+            neighbours: NeighbourCount::Exactly(2),
+            combiner: Combiner::Product
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Gear {
+    coord: Coord2D,
+    symbol: char,
+    /// IDs of the neighbouring parts
+    neighbours: Vec<usize>,
+    value: i64
+}
 
-            
-            for offs in &OFFSETS {
-                let potential_pos = Coord2D { x : gear.x + offs.x, y:gear.y + offs.y};
+struct Options {
+    symbol_filter: Option<SymbolFilter>,
+    tokenizer_mode: TokenizerMode,
+    neighbourhood: Neighbourhood,
+    gear_rule: GearRule,
+    list_gears: bool,
+    render_format: render::RenderFormat,
+    output: Option<String>
+}
 
-                let mut neighbors : Vec<&EnginePart> = self.parts.iter().filter(|part| part.digit_coords.contains(&potential_pos)).collect();
-               
-                for neigbor in neighbors {
-                    if !neighbouring_engine_parts.contains(&neigbor) {
-                        neighbouring_engine_parts.push(neigbor);
-                    }
+impl Options {
+    fn from_args(args: &[String]) -> Self {
+        let mut symbol_filter = None;
+        let mut tokenizer_mode = TokenizerMode::default();
+        let mut neighbourhood = Neighbourhood::default();
+        let mut gear_rule = GearRule::default();
+        let mut list_gears = false;
+        let mut render_format = render::RenderFormat::Ansi;
+        let mut output = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--symbols" => {
+                    let symbols = args.next().expect("Missing value for --symbols");
+                    symbol_filter = Some(SymbolFilter::Only(symbols.chars().collect()));
                 }
+                "--exclude-symbols" => {
+                    let exclude = args.next().expect("Missing value for --exclude-symbols");
+                    symbol_filter = Some(SymbolFilter::Inferred { exclude: exclude.chars().collect() });
+                }
+                "--signed" => {
+                    tokenizer_mode.signed = true;
+                }
+                "--wrap-lines" => {
+                    tokenizer_mode.wrap_lines = true;
+                }
+                "--wrap-edges" => {
+                    neighbourhood.boundary = BoundaryMode::Wrap;
+                }
+                "--von-neumann" => {
+                    neighbourhood.adjacency = Adjacency::VonNeumann;
+                }
+                "--gear-symbols" => {
+                    gear_rule.symbols = args.next().expect("Missing value for --gear-symbols").chars().collect();
+                }
+                "--gear-neighbours" => {
+                    gear_rule.neighbours = NeighbourCount::from_string(args.next().expect("Missing value for --gear-neighbours"));
+                }
+                "--gear-combine" => {
+                    gear_rule.combiner = Combiner::from_string(args.next().expect("Missing value for --gear-combine"));
+                }
+                "--list-gears" => {
+                    list_gears = true;
+                }
+                "--format" => {
+                    render_format = match args.next().expect("Missing value for --format").as_str() {
+                        "ansi" => render::RenderFormat::Ansi,
+                        "html" => render::RenderFormat::Html,
+                        _ => panic!("Unknown format, expected ansi or html")
+                    };
+                }
+                "--output" => {
+                    output = Some(args.next().expect("Missing value for --output").clone());
+                }
+                _ => panic!("Unknown option {:?}", arg)
             }
-           
-            if neighbouring_engine_parts.len() == 2 {
-                gear_ratio += (neighbouring_engine_parts[0].number * neighbouring_engine_parts[1].number);
-            }
+        }
 
+        Self {
+            symbol_filter,
+            tokenizer_mode,
+            neighbourhood,
+            gear_rule,
+            list_gears,
+            render_format,
+            output
         }
-        gear_ratio
     }
 }
 
-fn riddle_part_one(file_path: &String) {
+fn warn_symbols(schematic: &EngineSchematic) {
+    let symbol_chars : String = schematic.symbol_chars().into_iter().collect();
+    eprintln!("Treating {} distinct characters as symbols: {}", symbol_chars.len(), symbol_chars);
+}
+
+fn riddle_part_one(file_path: &String, options: &Options) {
     let text = fs::read_to_string(file_path).expect("Error reading file");
 
-    let schematic = EngineSchematic::from_string(&text, &SYMBOLS);    
+    let symbol_filter = options.symbol_filter.clone().unwrap_or(SymbolFilter::Inferred { exclude: Vec::new() });
+    let schematic = EngineSchematic::from_string(&text, &symbol_filter, &options.tokenizer_mode).unwrap_or_else(|error| panic!("{}", error)).with_neighbourhood(options.neighbourhood);
+    warn_symbols(&schematic);
     let part_sum = schematic.get_valid_engine_parts_sum();
     println!("{:?}", part_sum);
 }
 
-fn riddle_part_two(file_path: &String) {
+fn riddle_part_two(file_path: &String, options: &Options) {
     let text = fs::read_to_string(file_path).expect("Error reading file");
 
-    let gears = ['*'];
-    let schematic = EngineSchematic::from_string(&text, &gears);  
-    let gear_ratio = schematic.get_gear_ratio();
+    let symbol_filter = options.symbol_filter.clone().unwrap_or(SymbolFilter::Inferred { exclude: Vec::new() });
+    let schematic = EngineSchematic::from_string(&text, &symbol_filter, &options.tokenizer_mode).unwrap_or_else(|error| panic!("{}", error)).with_neighbourhood(options.neighbourhood);
+    warn_symbols(&schematic);
+
+    if options.list_gears {
+        for gear in schematic.find_gears(&options.gear_rule).unwrap_or_else(|error| panic!("{}", error)) {
+            let numbers : Vec<i64> = gear.neighbours.iter().map(|part_id| schematic.parts[*part_id].number).collect();
+            println!("{} at ({}, {}): {:?} => {}", gear.symbol, gear.coord.x, gear.coord.y, numbers, gear.value);
+        }
+    }
+
+    let gear_ratio = schematic.get_gear_ratio(&options.gear_rule).unwrap_or_else(|error| panic!("{}", error));
     println!("{:?}", gear_ratio);
 }
 
+fn render_schematic(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let symbol_filter = options.symbol_filter.clone().unwrap_or(SymbolFilter::Inferred { exclude: Vec::new() });
+    let schematic = EngineSchematic::from_string(&text, &symbol_filter, &options.tokenizer_mode).unwrap_or_else(|error| panic!("{}", error)).with_neighbourhood(options.neighbourhood);
+    warn_symbols(&schematic);
+
+    let gears = schematic.find_gears(&options.gear_rule).unwrap_or_else(|error| panic!("{}", error));
+    let rendered = render::render(&text, &schematic, &gears, options.render_format);
+
+    match &options.output {
+        Some(output) => fs::write(output, rendered).expect("Error writing file"),
+        None => print!("{}", rendered)
+    }
+}
+
+fn component_report(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let symbol_filter = options.symbol_filter.clone().unwrap_or(SymbolFilter::Inferred { exclude: Vec::new() });
+    let schematic = EngineSchematic::from_string(&text, &symbol_filter, &options.tokenizer_mode).unwrap_or_else(|error| panic!("{}", error)).with_neighbourhood(options.neighbourhood);
+    warn_symbols(&schematic);
+
+    let analysis = components::analyze(&schematic);
+    println!("Machines: {}", analysis.machines.len());
+
+    if let Some(machine) = analysis.largest_machine() {
+        let numbers : Vec<i64> = machine.parts.iter().map(|part_id| schematic.parts[*part_id].number).collect();
+        println!("Largest machine: total {}, {} parts {:?}, {} symbols", machine.total, numbers.len(), numbers, machine.symbols.len());
+    }
+
+    let isolated : Vec<i64> = analysis.isolated_parts.iter().map(|part_id| schematic.parts[*part_id].number).collect();
+    println!("Isolated parts: {:?}", isolated);
+
+    let lonely : Vec<String> = analysis.lonely_symbols.iter().map(|coord| format!("({}, {})", coord.x, coord.y)).collect();
+    println!("Symbols without parts: {}", lonely.join(", "));
+}
+
+/// Streams the schematic row by row, `-` reads from stdin.
+fn stream_schematic(file_path: &String, options: &Options) {
+    let symbol_filter = options.symbol_filter.clone().unwrap_or(SymbolFilter::Inferred { exclude: Vec::new() });
+    let print_row = |row_index: usize, totals: &streaming::StreamTotals| {
+        println!("Row {}: part sum {}, gear ratio {}", row_index, totals.part_sum, totals.gear_ratio);
+    };
+
+    let result = if file_path == "-" {
+        streaming::solve(io::stdin().lock(), &symbol_filter, &options.tokenizer_mode, options.neighbourhood, &options.gear_rule, print_row)
+    } else {
+        let file = fs::File::open(file_path).expect("Error reading file");
+        streaming::solve(io::BufReader::new(file), &symbol_filter, &options.tokenizer_mode, options.neighbourhood, &options.gear_rule, print_row)
+    };
+    let totals = result.unwrap_or_else(|error| panic!("{}", error));
+
+    println!("{:?}", totals.part_sum);
+    println!("{:?}", totals.gear_ratio);
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
+    if args.len() < 3 {
         panic!("Provide the input text file!");
     }
-    let riddle_num: u32 = args
-        .get(1)
-        .unwrap()
-        .parse()
-        .expect("Error parsing riddle num");
+    let command = args.get(1).unwrap();
+    let options = Options::from_args(&args[3..]);
 
-    match riddle_num {
-        1 => {
-            riddle_part_one(args.get(2).unwrap());
+    match command.as_str() {
+        "1" => {
+            riddle_part_one(args.get(2).unwrap(), &options);
+        }
+        "2" => {
+            riddle_part_two(args.get(2).unwrap(), &options);
+        }
+        "components" => {
+            component_report(args.get(2).unwrap(), &options);
         }
-        2 => {
-            riddle_part_two(args.get(2).unwrap());
+        "stream" => {
+            stream_schematic(args.get(2).unwrap(), &options);
+        }
+        "render" => {
+            render_schematic(args.get(2).unwrap(), &options);
         }
         _ => {
-            panic!("Unknown riddle part number");
+            panic!("Unknown riddle part number or command");
         }
     };
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(text: &str, mode: TokenizerMode) -> Vec<i64> {
+        let schematic = EngineSchematic::from_string(text, &SymbolFilter::Inferred { exclude: Vec::new() }, &mode).unwrap();
+        schematic.parts.iter().map(|part| part.number).collect()
+    }
+
+    #[test]
+    fn splits_numbers_per_row() {
+        let mode = TokenizerMode::default();
+        assert_eq!(numbers("12.34\n56...", mode), vec![12, 34, 56]);
+        assert_eq!(numbers("..12\n34..", mode), vec![12, 34]);
+    }
+
+    #[test]
+    fn minus_is_a_symbol_unless_signed() {
+        let text = "..-12.5-3";
+        assert_eq!(numbers(text, TokenizerMode::default()), vec![12, 5, 3]);
+        assert_eq!(numbers(text, TokenizerMode { signed: true, wrap_lines: false }), vec![-12, 5, 3]);
+
+        let schematic = EngineSchematic::from_string(text, &SymbolFilter::Inferred { exclude: Vec::new() }, &TokenizerMode { signed: true, wrap_lines: false }).unwrap();
+        assert_eq!(schematic.symbol_chars(), vec!['-']);
+        assert_eq!(schematic.get_valid_engine_parts_sum(), 8);
+    }
+
+    #[test]
+    fn joins_numbers_across_rows() {
+        let text = "..12\n34..\n5...";
+        assert_eq!(numbers(text, TokenizerMode { signed: false, wrap_lines: true }), vec![1234, 5]);
+        assert_eq!(numbers("...-\n7*..", TokenizerMode { signed: true, wrap_lines: true }), vec![-7]);
+    }
+
+    #[test]
+    fn reports_numbers_too_large_for_i64() {
+        let filter = SymbolFilter::Inferred { exclude: Vec::new() };
+        let mode = TokenizerMode { signed: false, wrap_lines: true };
+        assert_eq!(numbers("9223372036854775807*", mode), vec![i64::MAX]);
+
+        let error = EngineSchematic::from_string("12345678901234567890*", &filter, &mode).unwrap_err();
+        assert_eq!(error, SchematicError::PartNumberTooLarge { coord: Coord2D { x: 0, y: 0 } });
+        let error = EngineSchematic::from_string("*..1234567890\n1234567890.", &filter, &mode).unwrap_err();
+        assert_eq!(error, SchematicError::PartNumberTooLarge { coord: Coord2D { x: 3, y: 0 } });
+    }
+
+    #[test]
+    fn reports_overflowing_gears() {
+        let schematic = EngineSchematic::from_string("99999*99999\n99999.99999", &SymbolFilter::Inferred { exclude: Vec::new() }, &TokenizerMode::default()).unwrap();
+        let rule = GearRule { neighbours: NeighbourCount::Exactly(4), ..GearRule::default() };
+        assert_eq!(schematic.get_gear_ratio(&rule), Err(SchematicError::GearOverflow { coord: Coord2D { x: 5, y: 0 }, symbol: '*' }));
+
+        let rule = GearRule { neighbours: NeighbourCount::Exactly(4), combiner: Combiner::Sum, ..GearRule::default() };
+        assert_eq!(schematic.get_gear_ratio(&rule), Ok(4 * 99999));
+    }
+
+    fn schematic(text: &str, boundary: BoundaryMode, adjacency: Adjacency) -> EngineSchematic {
+        EngineSchematic::from_string(text, &SymbolFilter::Inferred { exclude: Vec::new() }, &TokenizerMode::default())
+            .unwrap()
+            .with_neighbourhood(Neighbourhood { boundary, adjacency })
+    }
+
+    fn coords(pairs: &[(isize, isize)]) -> Vec<Coord2D> {
+        pairs.iter().map(|(x, y)| Coord2D { x: *x, y: *y }).collect()
+    }
+
+    #[test]
+    fn wrapped_edges_reach_the_opposite_side() {
+        let text = "1..\n..*";
+        assert_eq!(schematic(text, BoundaryMode::Clipped, Adjacency::Moore).get_valid_engine_parts_sum(), 0);
+        assert_eq!(schematic(text, BoundaryMode::Wrap, Adjacency::Moore).get_valid_engine_parts_sum(), 1);
+
+        let corner = schematic(text, BoundaryMode::Wrap, Adjacency::VonNeumann);
+        assert_eq!(corner.neighbours(&Coord2D { x: 0, y: 0 }), coords(&[(0, 1), (2, 0), (1, 0), (0, 1)]));
+    }
+
+    #[test]
+    fn von_neumann_ignores_diagonals() {
+        let text = "1.\n.*";
+        assert_eq!(schematic(text, BoundaryMode::Clipped, Adjacency::Moore).get_valid_engine_parts_sum(), 1);
+        assert_eq!(schematic(text, BoundaryMode::Clipped, Adjacency::VonNeumann).get_valid_engine_parts_sum(), 0);
+        assert_eq!(schematic("1\n*", BoundaryMode::Clipped, Adjacency::VonNeumann).get_valid_engine_parts_sum(), 1);
+    }
+
+    #[test]
+    fn wrapping_tiny_grids_repeats_cells() {
+        // A single cell is its own neighbour in every direction
+        let single = schematic("*", BoundaryMode::Wrap, Adjacency::Moore);
+        assert_eq!(single.neighbours(&Coord2D { x: 0, y: 0 }), coords(&[(0, 0); 8]));
+        assert_eq!(single.get_valid_engine_parts_sum(), 0);
+
+        // With a width of 2 the left and right neighbour are the same cell
+        let pair = schematic("7*", BoundaryMode::Wrap, Adjacency::VonNeumann);
+        assert_eq!(pair.neighbours(&Coord2D { x: 1, y: 0 }), coords(&[(1, 0), (0, 0), (0, 0), (1, 0)]));
+        assert_eq!(pair.neighbouring_parts(&Coord2D { x: 1, y: 0 }), vec![0]);
+        assert_eq!(pair.get_valid_engine_parts_sum(), 7);
+
+        let rule = GearRule { neighbours: NeighbourCount::Exactly(1), ..GearRule::default() };
+        assert_eq!(pair.get_gear_ratio(&rule), Ok(7));
+        assert_eq!(pair.get_gear_ratio(&GearRule::default()), Ok(0));
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+
+use crate::{Cell, Coord2D, EngineSchematic, Gear};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderFormat {
+    Ansi,
+    Html
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Highlight {
+    Plain,
+    ValidPart,
+    UnconnectedPart,
+    Gear,
+    Symbol
+}
+
+impl Highlight {
+    fn ansi_code(&self) -> &'static str {
+        match self {
+            Highlight::Plain => "",
+            Highlight::ValidPart => "\x1b[32m",
+            Highlight::UnconnectedPart => "\x1b[31m",
+            Highlight::Gear => "\x1b[1;33m",
+            Highlight::Symbol => "\x1b[1m"
+        }
+    }
+
+    fn css_class(&self) -> &'static str {
+        match self {
+            Highlight::Plain => "",
+            Highlight::ValidPart => "valid",
+            Highlight::UnconnectedPart => "unconnected",
+            Highlight::Gear => "gear",
+            Highlight::Symbol => "symbol"
+        }
+    }
+}
+
+const HTML_STYLE : &str = ".valid { color: #2a2; } .unconnected { color: #c22; } .gear { color: #b80; font-weight: bold; } .symbol { font-weight: bold; }";
+
+/// Gear symbols and the parts next to them, collected once per render.
+struct GearCells {
+    symbols: HashSet<Coord2D>,
+    parts: HashSet<usize>
+}
+
+impl GearCells {
+    fn new(gears: &[Gear]) -> Self {
+        Self {
+            symbols: gears.iter().map(|gear| gear.coord.clone()).collect(),
+            parts: gears.iter().flat_map(|gear| gear.neighbours.iter().copied()).collect()
+        }
+    }
+}
+
+fn highlight(schematic: &EngineSchematic, gear_cells: &GearCells, is_valid: &[bool], coord: &Coord2D) -> Highlight {
+    match schematic.cell(coord) {
+        Cell::Empty => Highlight::Plain,
+        Cell::Part(part_id) => {
+            if gear_cells.parts.contains(&part_id) {
+                Highlight::Gear
+            } else if is_valid[part_id] {
+                Highlight::ValidPart
+            } else {
+                Highlight::UnconnectedPart
+            }
+        }
+        Cell::Symbol(_) => {
+            if gear_cells.symbols.contains(coord) {
+                Highlight::Gear
+            } else {
+                Highlight::Symbol
+            }
+        }
+    }
+}
+
+fn escape_html(c: char) -> String {
+    match c {
+        '<' => String::from("&lt;"),
+        '>' => String::from("&gt;"),
+        '&' => String::from("&amp;"),
+        _ => c.to_string()
+    }
+}
+
+/// One line per part: its number, the coordinates of its digits and the symbol that validated it.
+fn part_table(schematic: &EngineSchematic) -> Vec<String> {
+    schematic.parts.iter().enumerate().map(|(part_id, part)| {
+        let first = &part.digit_coords[0];
+        let last = &part.digit_coords[part.digit_coords.len() - 1];
+        let validated_by = match schematic.validating_symbol(part_id) {
+            Some((coord, symbol)) => format!("{} at ({}, {})", symbol, coord.x, coord.y),
+            None => String::from("-")
+        };
+        format!("{:>6} | ({}, {})-({}, {}) | {}", part.number, first.x, first.y, last.x, last.y, validated_by)
+    }).collect()
+}
+
+/// Re-emits the schematic text with valid parts, unconnected parts and gears
+/// (including their neighbouring parts) highlighted, followed by a part table.
+pub fn render(text: &str, schematic: &EngineSchematic, gears: &[Gear], format: RenderFormat) -> String {
+    let is_valid = schematic.valid_parts();
+    let gear_cells = GearCells::new(gears);
+
+    let mut grid = String::new();
+    for (row_index, row) in text.split('\n').enumerate() {
+        for (col_index, c) in row.chars().enumerate() {
+            let coord = Coord2D { x: col_index as isize, y: row_index as isize };
+            let highlight = highlight(schematic, &gear_cells, &is_valid, &coord);
+
+            match (format, highlight) {
+                (RenderFormat::Ansi, Highlight::Plain) => grid.push(c),
+                (RenderFormat::Ansi, _) => grid.push_str(&format!("{}{}\x1b[0m", highlight.ansi_code(), c)),
+                (RenderFormat::Html, Highlight::Plain) => grid.push_str(&escape_html(c)),
+                (RenderFormat::Html, _) => grid.push_str(&format!("<span class=\"{}\">{}</span>", highlight.css_class(), escape_html(c)))
+            }
+        }
+        grid.push('\n');
+    }
+
+    let header = String::from("  Part | Digits | Validated by");
+    let table = part_table(schematic);
+
+    match format {
+        RenderFormat::Ansi => format!("{}\n{}\n{}\n", grid, header, table.join("\n")),
+        RenderFormat::Html => {
+            let table : String = table.iter().map(|line| escape_line(line) + "\n").collect();
+            format!("<!DOCTYPE html>\n<html>\n<head>\n<style>{}</style>\n</head>\n<body>\n<pre>\n{}</pre>\n<pre>\n{}\n{}</pre>\n</body>\n</html>\n",
+                HTML_STYLE, grid, header, table)
+        }
+    }
+}
+
+fn escape_line(line: &str) -> String {
+    line.chars().map(escape_html).collect()
+}
@@ -0,0 +1,109 @@
+use std::io::BufRead;
+
+use crate::{BoundaryMode, Coord2D, EngineSchematic, GearRule, Neighbourhood, SchematicError, SymbolFilter, TokenizerMode};
+
+/// Running totals of a streamed schematic.
+#[derive(Debug, Default)]
+pub struct StreamTotals {
+    pub rows: usize,
+    pub part_sum: i64,
+    pub gear_ratio: i64
+}
+
+/// Moves an error found in the window to the row it belongs to, the middle
+/// row of the window being `row_index`.
+fn in_row(error: SchematicError, row_index: usize) -> SchematicError {
+    let shift = |coord: Coord2D| Coord2D { x: coord.x, y: coord.y + row_index as isize - 1 };
+    match error {
+        SchematicError::PartNumberTooLarge { coord } => SchematicError::PartNumberTooLarge { coord: shift(coord) },
+        SchematicError::GearOverflow { coord, symbol } => SchematicError::GearOverflow { coord: shift(coord), symbol },
+        SchematicError::GearRatioOverflow => SchematicError::GearRatioOverflow
+    }
+}
+
+/// Solves both parts while only ever holding three rows of the schematic.
+///
+/// Every row is evaluated once the row below it has been read: its parts are
+/// checked against the symbols of the window and its gears against the parts
+/// of the window. `on_row` is called with the index of each finished row and
+/// the totals so far.
+pub fn solve(input: impl BufRead, symbol_filter: &SymbolFilter, mode: &TokenizerMode, neighbourhood: Neighbourhood, rule: &GearRule, mut on_row: impl FnMut(usize, &StreamTotals)) -> Result<StreamTotals, SchematicError> {
+    if mode.wrap_lines {
+        panic!("Numbers wrapping across rows can not be streamed");
+    }
+    if neighbourhood.boundary == BoundaryMode::Wrap {
+        panic!("Wrapping edges need the whole schematic and can not be streamed");
+    }
+
+    let mut totals = StreamTotals::default();
+    let mut window : [String; 3] = Default::default();
+    let mut rows = input.lines().map(|line| line.expect("Error reading row"));
+
+    let mut next_row = rows.next();
+    while let Some(row) = next_row {
+        next_row = rows.next();
+
+        window.rotate_left(1);
+        window[1] = row;
+        window[2] = next_row.clone().unwrap_or_default();
+
+        let schematic = EngineSchematic::from_string(&window.join("\n"), symbol_filter, mode)
+            .map_err(|error| in_row(error, totals.rows))?
+            .with_neighbourhood(neighbourhood);
+
+        let is_valid = schematic.valid_parts();
+        for (part, valid) in schematic.parts.iter().zip(is_valid) {
+            if valid && part.digit_coords[0].y == 1 {
+                totals.part_sum += part.number;
+            }
+        }
+
+        for gear in schematic.find_gears(rule).map_err(|error| in_row(error, totals.rows))? {
+            if gear.coord.y == 1 {
+                totals.gear_ratio = totals.gear_ratio.checked_add(gear.value).ok_or(SchematicError::GearRatioOverflow)?;
+            }
+        }
+
+        on_row(totals.rows, &totals);
+        totals.rows += 1;
+    }
+    Ok(totals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Adjacency;
+
+    fn assert_matches_in_memory(text: &str, neighbourhood: Neighbourhood) {
+        let filter = SymbolFilter::Inferred { exclude: Vec::new() };
+        let mode = TokenizerMode::default();
+        let rule = GearRule::default();
+
+        let schematic = EngineSchematic::from_string(text, &filter, &mode).unwrap().with_neighbourhood(neighbourhood);
+        let totals = solve(text.as_bytes(), &filter, &mode, neighbourhood, &rule, |_, _| {}).unwrap();
+
+        assert_eq!(totals.rows, schematic.height);
+        assert_eq!(totals.part_sum, schematic.get_valid_engine_parts_sum());
+        assert_eq!(totals.gear_ratio, schematic.get_gear_ratio(&rule).unwrap());
+    }
+
+    #[test]
+    fn matches_in_memory_solver_on_sample() {
+        let totals = solve(include_str!("sample.txt").as_bytes(), &SymbolFilter::Inferred { exclude: Vec::new() }, &TokenizerMode::default(), Neighbourhood::default(), &GearRule::default(), |_, _| {}).unwrap();
+        assert_eq!((totals.part_sum, totals.gear_ratio), (4361, 467835));
+
+        assert_matches_in_memory(include_str!("sample.txt"), Neighbourhood::default());
+    }
+
+    #[test]
+    fn matches_in_memory_solver_at_window_edges() {
+        // Parts in the first and last row, at both ends of a row, touching
+        // symbols only in the row above or below, and gears whose parts sit
+        // in the rows above and below them.
+        let text = "12.....34\n..*...*..\n56.....78\n.........\n9*8....#1\n\n..3*4...\n7.......\n*.....5.\n.6....*.";
+        assert_matches_in_memory(text, Neighbourhood::default());
+        assert_matches_in_memory(text, Neighbourhood { adjacency: Adjacency::VonNeumann, ..Neighbourhood::default() });
+        assert_matches_in_memory("1*2", Neighbourhood::default());
+    }
+}
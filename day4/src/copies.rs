@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::ScratchTicket;
+
+/// How winning tickets hand out copies in part two.
+///
+/// A ticket with `k` matches wins `copies_per_match` copies of each of the
+/// tickets `stride`, `2 * stride`, ..., `k * stride` places after it. Targets
+/// past the last ticket are dropped, or start over at the first ticket with
+/// `wrap`. No ticket ever has more than `max_copies` instances, any further
+/// copies it would win are discarded. Wrapping needs `max_copies`, as copies
+/// that come back around can cycle forever.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyRule {
+    pub copies_per_match: u128,
+    pub stride: usize,
+    pub wrap: bool,
+    pub max_copies: Option<u128>,
+}
+
+impl Default for CopyRule {
+    fn default() -> Self {
+        Self {
+            copies_per_match: 1,
+            stride: 1,
+            wrap: false,
+            max_copies: None,
+        }
+    }
+}
+
+impl CopyRule {
+    fn cap(&self, copies: u128) -> u128 {
+        match self.max_copies {
+            Some(max_copies) => copies.min(max_copies),
+            None => copies,
+        }
+    }
+}
+
+/// The copies of a ticket no longer fit into a `u128`.
+#[derive(Debug, PartialEq)]
+pub struct CopyOverflow {
+    pub card: u32,
+}
+
+impl fmt::Display for CopyOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Card {}: the number of copies overflows", self.card)
+    }
+}
+
+/// Number of instances of every ticket after all copies have been won.
+/// The tickets must be sorted by ID without gaps, see `parse_tickets`.
+pub fn count_copies(tickets: &[ScratchTicket], rule: &CopyRule) -> Result<Vec<u128>, CopyOverflow> {
+    if rule.stride == 0 {
+        panic!("The copy stride must be at least one");
+    }
+
+    if rule.wrap {
+        Ok(count_wrapping_copies(tickets, rule))
+    } else {
+        count_forward_copies(tickets, rule)
+    }
+}
+
+/// Without wrapping, tickets only ever win copies of later tickets, so a
+/// single pass in order settles each count before it is needed. The copies a
+/// ticket hands to the following tickets are only recorded where that run of
+/// targets starts and ends, which keeps the whole count linear in the number
+/// of tickets. With a stride, each run only covers every `stride`th ticket, so
+/// the running count is carried along in steps of `stride` as well.
+fn count_forward_copies(tickets: &[ScratchTicket], rule: &CopyRule) -> Result<Vec<u128>, CopyOverflow> {
+    let ticket_count = tickets.len();
+
+    let mut copies: Vec<u128> = vec![0; ticket_count];
+    let mut won_copies: Vec<u128> = vec![0; ticket_count];
+    let mut starting: Vec<u128> = vec![0; ticket_count];
+    let mut ending: Vec<u128> = vec![0; ticket_count];
+
+    for (ticket_index, ticket) in tickets.iter().enumerate() {
+        let overflow = || CopyOverflow { card: ticket.id };
+
+        let carried = if ticket_index >= rule.stride { won_copies[ticket_index - rule.stride] } else { 0 };
+        won_copies[ticket_index] = carried.checked_add(starting[ticket_index]).ok_or_else(overflow)? - ending[ticket_index];
+        copies[ticket_index] = rule.cap(won_copies[ticket_index].checked_add(1).ok_or_else(overflow)?);
+
+        let handed_out = copies[ticket_index].checked_mul(rule.copies_per_match).ok_or_else(overflow)?;
+        let first_target = ticket_index.saturating_add(rule.stride);
+        let past_last_target = ticket_index.saturating_add((ticket.matching_numbers() as usize + 1).saturating_mul(rule.stride));
+        if ticket.matching_numbers() > 0 && first_target < ticket_count {
+            starting[first_target] = starting[first_target].checked_add(handed_out).ok_or_else(overflow)?;
+            if past_last_target < ticket_count {
+                ending[past_last_target] = ending[past_last_target].checked_add(handed_out).ok_or_else(overflow)?;
+            }
+        }
+    }
+
+    Ok(copies)
+}
+
+/// With wrapping, copies can flow back to earlier tickets, so instances are
+/// processed from a work queue until no ticket has unprocessed instances left.
+/// This only terminates if the number of instances is capped. The cap also
+/// bounds every count, so saturating arithmetic is exact here.
+fn count_wrapping_copies(tickets: &[ScratchTicket], rule: &CopyRule) -> Vec<u128> {
+    if rule.max_copies.is_none() {
+        panic!("Wrapping copies need a cap on the copies per ticket");
+    }
+
+    let ticket_count = tickets.len();
+    let mut copies: Vec<u128> = vec![rule.cap(1); ticket_count];
+    let mut unprocessed: Vec<u128> = copies.clone();
+    let mut queue: VecDeque<usize> = (0..ticket_count).collect();
+
+    while let Some(ticket_index) = queue.pop_front() {
+        let instances = unprocessed[ticket_index];
+        unprocessed[ticket_index] = 0;
+
+        for target in 1..=tickets[ticket_index].matching_numbers() as usize {
+            let target_index = (ticket_index + target * (rule.stride % ticket_count)) % ticket_count;
+
+            let won = rule.cap(copies[target_index].saturating_add(instances.saturating_mul(rule.copies_per_match))) - copies[target_index];
+            if won == 0 {
+                continue;
+            }
+            copies[target_index] += won;
+            if unprocessed[target_index] == 0 {
+                queue.push_back(target_index);
+            }
+            unprocessed[target_index] += won;
+        }
+    }
+
+    copies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_tickets;
+
+    /// The original part two solution, pushing every single instance through a queue.
+    fn simulate_copies(tickets: &[ScratchTicket], rule: &CopyRule) -> Vec<u128> {
+        let ticket_count = tickets.len();
+        let mut copies: Vec<u128> = vec![1; ticket_count];
+        let mut queue: VecDeque<usize> = (0..ticket_count).collect();
+
+        while let Some(ticket_index) = queue.pop_front() {
+            for target in 1..=tickets[ticket_index].matching_numbers() as usize {
+                let mut target_index = ticket_index + target * rule.stride;
+                if target_index >= ticket_count {
+                    if !rule.wrap {
+                        continue;
+                    }
+                    target_index %= ticket_count;
+                }
+                for _ in 0..rule.copies_per_match {
+                    if copies[target_index] < rule.max_copies.unwrap_or(u128::MAX) {
+                        copies[target_index] += 1;
+                        queue.push_back(target_index);
+                    }
+                }
+            }
+        }
+        copies
+    }
+
+    fn sample_tickets() -> Vec<ScratchTicket> {
+        parse_tickets(include_str!("sample.txt"), None)
+    }
+
+    #[test]
+    fn matches_simulation_on_sample() {
+        let tickets = sample_tickets();
+        let rule = CopyRule::default();
+
+        let copies = count_copies(&tickets, &rule).unwrap();
+        assert_eq!(copies, simulate_copies(&tickets, &rule));
+        assert_eq!(copies.iter().sum::<u128>(), 30);
+    }
+
+    #[test]
+    fn reports_overflow() {
+        let text: String = (1..=200)
+            .map(|id| format!("Card {}: 1 2 3 4 5 6 7 8 9 10 | 1 2 3 4 5 6 7 8 9 10\n", id))
+            .collect();
+        let tickets = parse_tickets(&text, None);
+
+        let error = count_copies(&tickets, &CopyRule::default()).unwrap_err();
+        assert!(error.card < 200);
+    }
+
+    fn assert_matches_simulation(rule: CopyRule) {
+        let tickets = sample_tickets();
+        assert_eq!(count_copies(&tickets, &rule).unwrap(), simulate_copies(&tickets, &rule), "{:?}", rule);
+    }
+
+    #[test]
+    fn matches_simulation_with_stride() {
+        for stride in 1..=7 {
+            assert_matches_simulation(CopyRule { stride, ..CopyRule::default() });
+        }
+    }
+
+    #[test]
+    fn matches_simulation_with_copies_per_match() {
+        for copies_per_match in 0..=3 {
+            assert_matches_simulation(CopyRule { copies_per_match, ..CopyRule::default() });
+            assert_matches_simulation(CopyRule { copies_per_match, stride: 2, ..CopyRule::default() });
+        }
+    }
+
+    #[test]
+    fn matches_simulation_with_cap() {
+        for max_copies in 1..=8 {
+            assert_matches_simulation(CopyRule { max_copies: Some(max_copies), ..CopyRule::default() });
+            assert_matches_simulation(CopyRule { max_copies: Some(max_copies), copies_per_match: 2, ..CopyRule::default() });
+        }
+    }
+
+    #[test]
+    fn matches_simulation_with_wrap_and_cap() {
+        for max_copies in 1..=8 {
+            for stride in 1..=3 {
+                assert_matches_simulation(CopyRule {
+                    wrap: true,
+                    max_copies: Some(max_copies),
+                    stride,
+                    ..CopyRule::default()
+                });
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Wrapping copies need a cap")]
+    fn wrap_without_cap_panics() {
+        let _ = count_copies(&sample_tickets(), &CopyRule { wrap: true, ..CopyRule::default() });
+    }
+}
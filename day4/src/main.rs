@@ -1,137 +1,539 @@
-use std::cmp;
-use std::collections::HashSet;
-use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs;
-use std::collections::VecDeque;
+use std::hint;
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+mod copies;
+mod number_set;
+mod report;
+mod scoring;
+
+use number_set::NumberSet;
+use scoring::ScoringRule;
 
 #[derive(Debug)]
 struct ScratchTicket {
-    winners: HashSet<u32>,
-    picks: HashSet<u32>,
+    id: u32,
+    winners: NumberSet,
+    picks: NumberSet,
 }
 
-impl ScratchTicket {
+/// A ticket as written in the input, including duplicate numbers.
+#[derive(Debug)]
+struct RawTicket {
+    id: u32,
+    winners: Vec<u32>,
+    picks: Vec<u32>,
+}
+
+impl RawTicket {
     fn from_string(text: &str) -> Self {
-        let card_number_part: &str = text.split(':').nth(1).unwrap();
+        let (card_id_part, card_number_part) = text.split_once(':').unwrap();
+        let card_id = card_id_part
+            .trim()
+            .strip_prefix("Card")
+            .expect("Ticket must start with Card")
+            .trim()
+            .parse::<u32>()
+            .expect("Error parsing card id");
         let number_parts: Vec<&str> = card_number_part.split('|').collect();
 
-        let winning_numbers: HashSet<u32> = HashSet::from_iter(
-            number_parts[0]
-                .split(' ')
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<u32>().unwrap()),
-        );
+        let winning_numbers: Vec<u32> = number_parts[0]
+            .split(' ')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<u32>().unwrap())
+            .collect();
 
-        let picked_numbers: HashSet<u32> = HashSet::from_iter(
-            number_parts[1]
-                .split(' ')
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<u32>().unwrap()),
-        );
+        let picked_numbers: Vec<u32> = number_parts[1]
+            .split(' ')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<u32>().unwrap())
+            .collect();
 
         Self {
+            id: card_id,
             winners: winning_numbers,
             picks: picked_numbers,
         }
     }
+}
+
+impl ScratchTicket {
+    fn from_raw(raw: &RawTicket) -> Self {
+        Self {
+            id: raw.id,
+            winners: NumberSet::from_numbers(&raw.winners),
+            picks: NumberSet::from_numbers(&raw.picks),
+        }
+    }
 
-    fn points(&self) -> u32 {
-        let matches = self.winners.intersection(&self.picks).count() as i32;
+    fn points(&self, rule: &dyn ScoringRule) -> u64 {
+        let matches = self.matching_numbers();
         if matches > 0 {
-            2u32.pow(cmp::max(matches - 1, 0) as u32)
+            rule.score(matches)
         } else {
             0
         }
     }
 
     fn matching_numbers(&self) -> u32 {
-        self.winners.intersection(&self.picks).count() as u32
+        self.winners.intersection_len(&self.picks)
     }
  }
 
-fn riddle_part_one(file_path: &String) {
-    let text = fs::read_to_string(file_path).expect("Error reading file");
+#[derive(Debug, PartialEq)]
+enum TicketIdError {
+    /// IDs start at 1, so card 0 can never be valid
+    ZeroId,
+    Duplicate(u32),
+    /// IDs between `after` and `next` are missing
+    Gap { after: u32, next: u32 },
+}
 
-    let mut tickets: Vec<ScratchTicket> = Vec::new();
-    for line in text.split('\n') {
-        let ticket = ScratchTicket::from_string(line);
-        tickets.push(ticket);
+impl fmt::Display for TicketIdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TicketIdError::ZeroId => write!(f, "Card 0 is not allowed, IDs start at 1"),
+            TicketIdError::Duplicate(id) => write!(f, "Card {} appears more than once", id),
+            TicketIdError::Gap { after, next } if after + 2 == *next => write!(f, "Card {} is missing", after + 1),
+            TicketIdError::Gap { after, next } => write!(f, "Cards {} to {} are missing", after + 1, next - 1),
+        }
     }
+}
 
-    let ticket_points: Vec<u32> = tickets.iter().map(|ticket| ticket.points()).collect();
-    let point_sum: u32 = ticket_points.iter().sum();
-    println!("Ticket Points: {:?}", ticket_points);
-    println!("Sum: {:?}", point_sum);
+/// Checks that the ticket IDs, in any order, are exactly `1..=n`.
+fn validate_ticket_ids(tickets: &[ScratchTicket]) -> Result<(), Vec<TicketIdError>> {
+    let mut ids: Vec<u32> = tickets.iter().map(|ticket| ticket.id).collect();
+    ids.sort();
+
+    let mut errors: Vec<TicketIdError> = Vec::new();
+    if ids.first() == Some(&0) {
+        errors.push(TicketIdError::ZeroId);
+    }
+
+    let mut last_id: Option<u32> = None;
+    for id in ids.into_iter().filter(|id| *id != 0) {
+        match last_id {
+            Some(last) if id == last && errors.last() != Some(&TicketIdError::Duplicate(id)) => {
+                errors.push(TicketIdError::Duplicate(id))
+            }
+            Some(last) if id > last + 1 => errors.push(TicketIdError::Gap { after: last, next: id }),
+            None if id > 1 => errors.push(TicketIdError::Gap { after: 0, next: id }),
+            _ => {}
+        }
+        last_id = Some(id);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
 }
 
-fn riddle_part_two(file_path: &String) {
-    let text = fs::read_to_string(file_path).expect("Error reading file");
+/// Additional checks on the numbers of every ticket.
+#[derive(Debug, Clone, Default)]
+struct StrictMode {
+    /// Allowed range for all winning and picked numbers
+    range: Option<RangeInclusive<u32>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum TicketNumberError {
+    DuplicateWinner { card: u32, number: u32 },
+    DuplicatePick { card: u32, number: u32 },
+    WinnerCount { card: u32, expected: usize, found: usize },
+    PickCount { card: u32, expected: usize, found: usize },
+    OutOfRange { card: u32, number: u32 },
+}
+
+impl fmt::Display for TicketNumberError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TicketNumberError::DuplicateWinner { card, number } => {
+                write!(f, "Card {}: winning number {} appears more than once", card, number)
+            }
+            TicketNumberError::DuplicatePick { card, number } => {
+                write!(f, "Card {}: picked number {} appears more than once", card, number)
+            }
+            TicketNumberError::WinnerCount { card, expected, found } => {
+                write!(f, "Card {}: {} winning numbers instead of {}", card, found, expected)
+            }
+            TicketNumberError::PickCount { card, expected, found } => {
+                write!(f, "Card {}: {} picked numbers instead of {}", card, found, expected)
+            }
+            TicketNumberError::OutOfRange { card, number } => write!(f, "Card {}: number {} is out of range", card, number),
+        }
+    }
+}
 
-    let mut tickets: Vec<ScratchTicket> = Vec::new();
-    for line in text.split('\n') {
-        let ticket = ScratchTicket::from_string(line);
-        tickets.push(ticket);
+fn duplicates(numbers: &[u32]) -> Vec<u32> {
+    let mut sorted = numbers.to_vec();
+    sorted.sort();
+    let mut duplicates: Vec<u32> = sorted.windows(2).filter(|pair| pair[0] == pair[1]).map(|pair| pair[0]).collect();
+    duplicates.dedup();
+    duplicates
+}
+
+/// The value occurring most often, ties go to the value seen first.
+fn most_common(values: impl Iterator<Item = usize>) -> Option<usize> {
+    let mut counts: Vec<(usize, usize)> = Vec::new();
+    for value in values {
+        match counts.iter_mut().find(|(known, _)| *known == value) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
     }
+    counts.iter().rev().max_by_key(|(_, count)| *count).map(|(value, _)| *value)
+}
 
+/// Checks that no number repeats within one side of a ticket, that every
+/// ticket has as many winning and picked numbers as most tickets do and that
+/// all numbers fall into the declared range.
+fn validate_ticket_numbers(tickets: &[RawTicket], strict: &StrictMode) -> Result<(), Vec<TicketNumberError>> {
+    let mut errors: Vec<TicketNumberError> = Vec::new();
 
-    let mut ticket_pile: HashMap<usize, usize> = HashMap::new();
-    let mut ticket_queue : VecDeque<(usize, &ScratchTicket)> = VecDeque::new();
+    let expected_counts = most_common(tickets.iter().map(|ticket| ticket.winners.len()))
+        .zip(most_common(tickets.iter().map(|ticket| ticket.picks.len())));
 
-    let ticket_count = tickets.len();
+    for ticket in tickets {
+        for number in duplicates(&ticket.winners) {
+            errors.push(TicketNumberError::DuplicateWinner { card: ticket.id, number });
+        }
+        for number in duplicates(&ticket.picks) {
+            errors.push(TicketNumberError::DuplicatePick { card: ticket.id, number });
+        }
 
-    let mut incr_ticket_count = |ticket_index| {
-        ticket_pile.insert(ticket_index, ticket_pile.get(&ticket_index).unwrap_or(&0) + 1);
-    };
+        if let Some((expected_winners, expected_picks)) = expected_counts {
+            if ticket.winners.len() != expected_winners {
+                errors.push(TicketNumberError::WinnerCount {
+                    card: ticket.id,
+                    expected: expected_winners,
+                    found: ticket.winners.len(),
+                });
+            }
+            if ticket.picks.len() != expected_picks {
+                errors.push(TicketNumberError::PickCount {
+                    card: ticket.id,
+                    expected: expected_picks,
+                    found: ticket.picks.len(),
+                });
+            }
+        }
+
+        if let Some(range) = &strict.range {
+            for number in ticket.winners.iter().chain(ticket.picks.iter()) {
+                if !range.contains(number) {
+                    errors.push(TicketNumberError::OutOfRange { card: ticket.id, number: *number });
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
 
-    for (ticket_index, ticket) in tickets.iter().enumerate() {
-        let ticket_points = ticket.matching_numbers();
-        incr_ticket_count(ticket_index);
+/// Parses all tickets and orders them by ID, panicking on duplicate or missing IDs.
+/// With a strict mode, the numbers of every ticket are validated as well.
+fn parse_tickets(text: &str, strict: Option<&StrictMode>) -> Vec<ScratchTicket> {
+    let raw_tickets: Vec<RawTicket> = text
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(RawTicket::from_string)
+        .collect();
 
-        let end_index = cmp::min(ticket_index + 1 + ticket_points as usize, ticket_count);
-        for copy_index in ticket_index+1..end_index {
-            let copy_ticket = &tickets[copy_index];
+    if let Some(strict) = strict {
+        if let Err(errors) = validate_ticket_numbers(&raw_tickets, strict) {
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+            panic!("Invalid ticket numbers");
+        }
+    }
 
-            ticket_queue.push_back((copy_index, copy_ticket));
+    let mut tickets: Vec<ScratchTicket> = raw_tickets.iter().map(ScratchTicket::from_raw).collect();
+
+    if let Err(errors) = validate_ticket_ids(&tickets) {
+        for error in &errors {
+            eprintln!("{}", error);
         }
+        panic!("Invalid ticket IDs");
     }
 
-    while let Some((ticket_index, ticket)) = ticket_queue.pop_front() {
-        let ticket_points = ticket.matching_numbers();
-        incr_ticket_count(ticket_index);
+    tickets.sort_by_key(|ticket| ticket.id);
+    tickets
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+struct Options {
+    scoring: Box<dyn ScoringRule>,
+    copy_rule: copies::CopyRule,
+    iterations: usize,
+    format: OutputFormat,
+    top: usize,
+    strict: Option<StrictMode>,
+}
+
+impl Options {
+    fn from_args(args: &[String]) -> Self {
+        let mut scoring: Box<dyn ScoringRule> = Box::new(scoring::Doubling);
+        let mut copy_rule = copies::CopyRule::default();
+        let mut iterations = 1000;
+        let mut format = OutputFormat::Text;
+        let mut top = 10;
+        let mut strict: Option<StrictMode> = None;
 
-        let end_index = cmp::min(ticket_index + 1 + ticket_points as usize, ticket_count);
-        for copy_index in ticket_index+1..end_index {
-            let copy_ticket = &tickets[copy_index];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scoring" => {
+                    scoring = scoring::from_string(args.next().expect("Missing value for --scoring"));
+                }
+                "--copies-per-match" => {
+                    copy_rule.copies_per_match = args
+                        .next()
+                        .expect("Missing value for --copies-per-match")
+                        .parse::<u128>()
+                        .expect("Error parsing copies per match");
+                }
+                "--stride" => {
+                    copy_rule.stride = args
+                        .next()
+                        .expect("Missing value for --stride")
+                        .parse::<usize>()
+                        .expect("Error parsing stride");
+                }
+                "--wrap" => {
+                    copy_rule.wrap = true;
+                }
+                "--max-copies" => {
+                    copy_rule.max_copies = Some(
+                        args.next()
+                            .expect("Missing value for --max-copies")
+                            .parse::<u128>()
+                            .expect("Error parsing max copies"),
+                    );
+                }
+                "--iterations" => {
+                    iterations = args
+                        .next()
+                        .expect("Missing value for --iterations")
+                        .parse::<usize>()
+                        .expect("Error parsing iterations");
+                }
+                "--format" => {
+                    format = match args.next().expect("Missing value for --format").as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        _ => panic!("Unknown format, expected text or json"),
+                    };
+                }
+                "--top" => {
+                    top = args
+                        .next()
+                        .expect("Missing value for --top")
+                        .parse::<usize>()
+                        .expect("Error parsing top");
+                }
+                "--strict" => {
+                    strict.get_or_insert_with(StrictMode::default);
+                }
+                "--range" => {
+                    let range = args.next().expect("Missing value for --range");
+                    let (low, high) = range.split_once('-').expect("Range must look like low-high");
+                    let low = low.parse::<u32>().expect("Error parsing range start");
+                    let high = high.parse::<u32>().expect("Error parsing range end");
+                    strict.get_or_insert_with(StrictMode::default).range = Some(low..=high);
+                }
+                _ => panic!("Unknown option {:?}", arg),
+            }
+        }
 
-            ticket_queue.push_back((copy_index, copy_ticket));
+        // Copies can cycle forever once they wrap around, only a cap guarantees the count ends
+        if copy_rule.wrap && copy_rule.max_copies.is_none() {
+            panic!("--wrap requires --max-copies");
+        }
+
+        Self {
+            scoring,
+            copy_rule,
+            iterations,
+            format,
+            top,
+            strict,
         }
     }
+}
+
+fn riddle_part_one(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let tickets = parse_tickets(&text, options.strict.as_ref());
+
+    let ticket_points: Vec<u64> = tickets.iter().map(|ticket| ticket.points(options.scoring.as_ref())).collect();
+    let point_sum: u64 = ticket_points.iter().fold(0, |sum, points| sum.saturating_add(*points));
+    println!("Ticket Points: {:?}", ticket_points);
+    println!("Sum: {:?}", point_sum);
+}
 
-    let ticket_count : usize = ticket_pile.iter().map(|(k,v)| v).sum();
+fn riddle_part_two(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let tickets = parse_tickets(&text, options.strict.as_ref());
+
+    let ticket_copies = match copies::count_copies(&tickets, &options.copy_rule) {
+        Ok(ticket_copies) => ticket_copies,
+        Err(error) => panic!("{}", error),
+    };
+    let ticket_count: u128 = ticket_copies
+        .iter()
+        .try_fold(0u128, |sum, copies| sum.checked_add(*copies))
+        .expect("The total number of tickets overflows");
+    println!("Ticket Copies: {:?}", ticket_copies);
     println!("Ticket Count: {:?}", ticket_count);
 }
 
+fn ticket_report(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let tickets = parse_tickets(&text, options.strict.as_ref());
+    let report = match report::TicketReport::new(&tickets, &options.copy_rule, options.top) {
+        Ok(report) => report,
+        Err(error) => panic!("{}", error),
+    };
+    print!("{}", report.render(options.format));
+}
+
+/// Times match counting with the bitset representation against plain `HashSet`s.
+fn benchmark(file_path: &String, options: &Options) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
+
+    let tickets = parse_tickets(&text, options.strict.as_ref());
+    let hashed: Vec<(NumberSet, NumberSet)> = tickets
+        .iter()
+        .map(|ticket| {
+            let winners: Vec<u32> = ticket.winners.iter().collect();
+            let picks: Vec<u32> = ticket.picks.iter().collect();
+            (NumberSet::hashed(&winners), NumberSet::hashed(&picks))
+        })
+        .collect();
+
+    let start = Instant::now();
+    for _ in 0..options.iterations {
+        for ticket in &tickets {
+            hint::black_box(hint::black_box(ticket).matching_numbers());
+        }
+    }
+    let compact_time = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..options.iterations {
+        for (winners, picks) in &hashed {
+            hint::black_box(hint::black_box(winners).intersection_len(picks));
+        }
+    }
+    let hashed_time = start.elapsed();
+
+    let per_ticket = |elapsed: std::time::Duration| elapsed.as_nanos() as f64 / (options.iterations * tickets.len()).max(1) as f64;
+    println!("Compact: {:?} ({:.1} ns per ticket)", compact_time, per_ticket(compact_time));
+    println!("HashSet: {:?} ({:.1} ns per ticket)", hashed_time, per_ticket(hashed_time));
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
+    if args.len() < 3 {
         panic!("Provide the input text file!");
     }
-    let riddle_num: u32 = args
-        .get(1)
-        .unwrap()
-        .parse()
-        .expect("Error parsing riddle num");
+    let command = args.get(1).unwrap();
+    let options = Options::from_args(&args[3..]);
 
-    match riddle_num {
-        1 => {
-            riddle_part_one(args.get(2).unwrap());
+    match command.as_str() {
+        "1" => {
+            riddle_part_one(args.get(2).unwrap(), &options);
+        }
+        "2" => {
+            riddle_part_two(args.get(2).unwrap(), &options);
         }
-        2 => {
-            riddle_part_two(args.get(2).unwrap());
+        "report" => {
+            ticket_report(args.get(2).unwrap(), &options);
+        }
+        "bench" => {
+            benchmark(args.get(2).unwrap(), &options);
         }
         _ => {
-            panic!("Unknown riddle part number");
+            panic!("Unknown riddle part number or command");
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_tickets(text: &str) -> Vec<RawTicket> {
+        text.lines().map(RawTicket::from_string).collect()
+    }
+
+    fn id_errors(ids: &[u32]) -> Result<(), Vec<TicketIdError>> {
+        let tickets: Vec<ScratchTicket> = ids
+            .iter()
+            .map(|id| ScratchTicket::from_raw(&RawTicket::from_string(&format!("Card {}: 1 | 2", id))))
+            .collect();
+        validate_ticket_ids(&tickets)
+    }
+
+    #[test]
+    fn ticket_ids_may_come_in_any_order() {
+        assert_eq!(id_errors(&[3, 1, 2]), Ok(()));
+    }
+
+    #[test]
+    fn reports_duplicate_ticket_ids_once() {
+        assert_eq!(id_errors(&[1, 2, 2, 2, 3]), Err(vec![TicketIdError::Duplicate(2)]));
+    }
+
+    #[test]
+    fn reports_gaps_between_ticket_ids() {
+        assert_eq!(
+            id_errors(&[2, 3, 6]),
+            Err(vec![TicketIdError::Gap { after: 0, next: 2 }, TicketIdError::Gap { after: 3, next: 6 }])
+        );
+    }
+
+    #[test]
+    fn reports_ticket_id_zero_on_its_own() {
+        assert_eq!(id_errors(&[0, 1, 2]), Err(vec![TicketIdError::ZeroId]));
+        assert_eq!(id_errors(&[0, 0, 1]), Err(vec![TicketIdError::ZeroId]));
+        assert_eq!(id_errors(&[0]), Err(vec![TicketIdError::ZeroId]));
+    }
+
+    #[test]
+    fn counts_follow_the_majority() {
+        let tickets = raw_tickets("Card 1: 1 2 | 3\nCard 2: 1 2 3 | 4 5\nCard 3: 4 5 6 | 7 8");
+        let errors = validate_ticket_numbers(&tickets, &StrictMode::default()).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                TicketNumberError::WinnerCount { card: 1, expected: 3, found: 2 },
+                TicketNumberError::PickCount { card: 1, expected: 2, found: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn count_ties_go_to_the_first_ticket() {
+        assert_eq!(most_common([3, 2, 2, 3].into_iter()), Some(3));
+        assert_eq!(most_common([].into_iter()), None);
+    }
+}
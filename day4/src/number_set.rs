@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+/// Set of ticket numbers. Numbers below 128 are kept as bits of a `u128`,
+/// so intersecting two such sets is a single `and` plus a popcount. Sets with
+/// any larger number fall back to a `HashSet`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NumberSet {
+    Bits(u128),
+    Hashed(HashSet<u32>),
+}
+
+impl NumberSet {
+    pub fn from_numbers(numbers: &[u32]) -> Self {
+        if numbers.iter().all(|number| *number < u128::BITS) {
+            NumberSet::Bits(numbers.iter().fold(0, |bits, number| bits | (1 << number)))
+        } else {
+            NumberSet::hashed(numbers)
+        }
+    }
+
+    /// Always uses the `HashSet` representation.
+    pub fn hashed(numbers: &[u32]) -> Self {
+        NumberSet::Hashed(numbers.iter().copied().collect())
+    }
+
+    pub fn contains(&self, number: u32) -> bool {
+        match self {
+            NumberSet::Bits(bits) => number < u128::BITS && bits & (1 << number) != 0,
+            NumberSet::Hashed(numbers) => numbers.contains(&number),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            NumberSet::Bits(bits) => bits.count_ones() as usize,
+            NumberSet::Hashed(numbers) => numbers.len(),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u32> + '_> {
+        match self {
+            NumberSet::Bits(bits) => Box::new((0..u128::BITS).filter(move |number| bits & (1 << number) != 0)),
+            NumberSet::Hashed(numbers) => Box::new(numbers.iter().copied()),
+        }
+    }
+
+    /// Number of values contained in both sets.
+    pub fn intersection_len(&self, other: &NumberSet) -> u32 {
+        match (self, other) {
+            (NumberSet::Bits(a), NumberSet::Bits(b)) => (a & b).count_ones(),
+            (NumberSet::Hashed(a), NumberSet::Hashed(b)) => a.intersection(b).count() as u32,
+            _ => {
+                let (smaller, larger) = if self.len() <= other.len() { (self, other) } else { (other, self) };
+                smaller.iter().filter(|number| larger.contains(*number)).count() as u32
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected_len(a: &[u32], b: &[u32]) -> u32 {
+        let a: HashSet<u32> = a.iter().copied().collect();
+        let b: HashSet<u32> = b.iter().copied().collect();
+        a.intersection(&b).count() as u32
+    }
+
+    #[test]
+    fn every_representation_intersects_like_a_hash_set() {
+        let cases: [(&[u32], &[u32]); 6] = [
+            (&[], &[]),
+            (&[1, 2, 3], &[]),
+            (&[0, 5, 64, 127], &[0, 64, 100, 127]),
+            (&[41, 48, 83, 86, 17], &[83, 86, 6, 31, 17, 9, 48, 53]),
+            (&[3, 3, 7], &[3, 7, 7, 8]),
+            (&[1, 2, 3, 4], &[5, 6, 7]),
+        ];
+        for (a, b) in cases {
+            let expected = expected_len(a, b);
+            let (bits_a, bits_b) = (NumberSet::from_numbers(a), NumberSet::from_numbers(b));
+            let (hashed_a, hashed_b) = (NumberSet::hashed(a), NumberSet::hashed(b));
+            assert!(matches!(bits_a, NumberSet::Bits(_)));
+
+            assert_eq!(bits_a.intersection_len(&bits_b), expected, "{:?} and {:?} as bits", a, b);
+            assert_eq!(hashed_a.intersection_len(&hashed_b), expected, "{:?} and {:?} hashed", a, b);
+            assert_eq!(bits_a.intersection_len(&hashed_b), expected, "{:?} as bits and {:?} hashed", a, b);
+            assert_eq!(hashed_a.intersection_len(&bits_b), expected, "{:?} hashed and {:?} as bits", a, b);
+        }
+    }
+
+    #[test]
+    fn numbers_past_the_bits_are_hashed() {
+        let small: &[u32] = &[1, 127, 50];
+        let large: &[u32] = &[127, 128, 1000, 1];
+        let large_set = NumberSet::from_numbers(large);
+        assert!(matches!(large_set, NumberSet::Hashed(_)));
+        assert!(!NumberSet::from_numbers(small).contains(1000));
+
+        let expected = expected_len(small, large);
+        assert_eq!(NumberSet::from_numbers(small).intersection_len(&large_set), expected);
+        assert_eq!(large_set.intersection_len(&NumberSet::from_numbers(small)), expected);
+        assert_eq!(NumberSet::hashed(small).intersection_len(&large_set), expected);
+    }
+}
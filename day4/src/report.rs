@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::copies::{self, CopyOverflow, CopyRule};
+use crate::{OutputFormat, ScratchTicket};
+
+/// How many copies a ticket hands out to other tickets in part two.
+fn handed_out_copies(tickets: &[ScratchTicket], copies: &[u128], rule: &CopyRule) -> Result<Vec<u128>, CopyOverflow> {
+    tickets
+        .iter()
+        .enumerate()
+        .map(|(ticket_index, ticket)| {
+            let targets = (1..=ticket.matching_numbers() as usize)
+                .filter(|target| rule.wrap || ticket_index.saturating_add(target.saturating_mul(rule.stride)) < tickets.len())
+                .count();
+            copies[ticket_index]
+                .checked_mul(targets as u128)
+                .and_then(|copies| copies.checked_mul(rule.copies_per_match))
+                .ok_or(CopyOverflow { card: ticket.id })
+        })
+        .collect()
+}
+
+/// Label of the power of two bucket a copy count falls into, `1`, `2-3`, `4-7`, ...
+fn copy_bucket(copies: u128) -> (u32, String) {
+    let exponent = copies.max(1).ilog2();
+    let low = 1u128 << exponent;
+    let high = low - 1 + low;
+    if low == high {
+        (exponent, low.to_string())
+    } else {
+        (exponent, format!("{}-{}", low, high))
+    }
+}
+
+pub struct TicketReport {
+    /// Number of tickets per match count
+    match_histogram: BTreeMap<u32, usize>,
+    /// Numbers that matched on the most tickets, with the number of tickets
+    top_winning_numbers: Vec<(u32, usize)>,
+    /// Tickets handing out the most copies, as `(id, copies)`
+    top_copy_sources: Vec<(u32, u128)>,
+    /// Number of tickets per power of two range of instances
+    copy_distribution: BTreeMap<u32, (String, usize)>,
+}
+
+impl TicketReport {
+    pub fn new(tickets: &[ScratchTicket], rule: &CopyRule, top: usize) -> Result<Self, CopyOverflow> {
+        let mut match_histogram: BTreeMap<u32, usize> = BTreeMap::new();
+        let mut winning_numbers: HashMap<u32, usize> = HashMap::new();
+        for ticket in tickets {
+            *match_histogram.entry(ticket.matching_numbers()).or_insert(0) += 1;
+            for number in ticket.winners.iter().filter(|number| ticket.picks.contains(*number)) {
+                *winning_numbers.entry(number).or_insert(0) += 1;
+            }
+        }
+
+        let mut top_winning_numbers: Vec<(u32, usize)> = winning_numbers.into_iter().collect();
+        top_winning_numbers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        top_winning_numbers.truncate(top);
+
+        let ticket_copies = copies::count_copies(tickets, rule)?;
+        let handed_out = handed_out_copies(tickets, &ticket_copies, rule)?;
+        let mut top_copy_sources: Vec<(u32, u128)> = tickets.iter().map(|ticket| ticket.id).zip(handed_out).collect();
+        top_copy_sources.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        top_copy_sources.truncate(top);
+
+        let mut copy_distribution: BTreeMap<u32, (String, usize)> = BTreeMap::new();
+        for copies in ticket_copies {
+            let (exponent, label) = copy_bucket(copies);
+            copy_distribution.entry(exponent).or_insert((label, 0)).1 += 1;
+        }
+
+        Ok(Self {
+            match_histogram,
+            top_winning_numbers,
+            top_copy_sources,
+            copy_distribution,
+        })
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::from("Tickets per match count\n");
+        for (matches, count) in &self.match_histogram {
+            text.push_str(&format!("  {:>3} | {} ({})\n", matches, "#".repeat(*count), count));
+        }
+
+        text.push_str("Most frequently winning numbers\n");
+        for (number, count) in &self.top_winning_numbers {
+            text.push_str(&format!("  {:>3}: {} tickets\n", number, count));
+        }
+
+        text.push_str("Tickets handing out the most copies\n");
+        for (id, copies) in &self.top_copy_sources {
+            text.push_str(&format!("  Card {}: {} copies\n", id, copies));
+        }
+
+        text.push_str("Tickets per number of instances\n");
+        for (label, count) in self.copy_distribution.values() {
+            text.push_str(&format!("  {:>11} | {}\n", label, count));
+        }
+        text
+    }
+
+    fn to_json(&self) -> String {
+        let match_histogram: Vec<String> = self
+            .match_histogram
+            .iter()
+            .map(|(matches, count)| format!("\"{}\":{}", matches, count))
+            .collect();
+        let top_winning_numbers: Vec<String> = self
+            .top_winning_numbers
+            .iter()
+            .map(|(number, count)| format!("{{\"number\":{},\"tickets\":{}}}", number, count))
+            .collect();
+        let top_copy_sources: Vec<String> = self
+            .top_copy_sources
+            .iter()
+            .map(|(id, copies)| format!("{{\"card\":{},\"copies\":{}}}", id, copies))
+            .collect();
+        let copy_distribution: Vec<String> = self
+            .copy_distribution
+            .values()
+            .map(|(label, count)| format!("\"{}\":{}", label, count))
+            .collect();
+
+        format!(
+            "{{\"match_histogram\":{{{}}},\"top_winning_numbers\":[{}],\"top_copy_sources\":[{}],\"copy_distribution\":{{{}}}}}",
+            match_histogram.join(","),
+            top_winning_numbers.join(","),
+            top_copy_sources.join(","),
+            copy_distribution.join(",")
+        )
+    }
+
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Text => self.to_text(),
+            OutputFormat::Json => self.to_json() + "\n",
+        }
+    }
+}
@@ -0,0 +1,296 @@
+/// Turns the number of matching numbers on a ticket into points.
+/// Tickets without any match are worth nothing regardless of the rule.
+/// Scores too large for a `u64` saturate at `u64::MAX`.
+pub trait ScoringRule {
+    fn score(&self, matches: u32) -> u64;
+}
+
+/// `1, 2, 4, 8, ...`, the riddle rule.
+pub struct Doubling;
+
+impl ScoringRule for Doubling {
+    fn score(&self, matches: u32) -> u64 {
+        match matches {
+            0 => 0,
+            _ => 2u64.saturating_pow(matches - 1),
+        }
+    }
+}
+
+/// One point per match.
+pub struct Linear;
+
+impl ScoringRule for Linear {
+    fn score(&self, matches: u32) -> u64 {
+        matches as u64
+    }
+}
+
+/// `1, 1, 2, 3, 5, ...`
+pub struct Fibonacci;
+
+impl ScoringRule for Fibonacci {
+    fn score(&self, matches: u32) -> u64 {
+        if matches == 0 {
+            return 0;
+        }
+        let (mut current, mut next) = (1u64, 1u64);
+        for _ in 1..matches {
+            (current, next) = (next, current.saturating_add(next));
+        }
+        current
+    }
+}
+
+/// Points for `1, 2, 3, ...` matches, more matches than entries score like the last entry.
+pub struct LookupTable {
+    pub points: Vec<u64>,
+}
+
+impl ScoringRule for LookupTable {
+    fn score(&self, matches: u32) -> u64 {
+        if matches == 0 {
+            return 0;
+        }
+        let index = (matches as usize - 1).min(self.points.len() - 1);
+        self.points[index]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(i64),
+    Matches,
+    Binary(char, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+}
+
+impl Expr {
+    /// Clamps an exponent into `u32`, keeping its parity so `-1` still alternates.
+    fn exponent(rhs: i64) -> u32 {
+        u32::try_from(rhs).unwrap_or(u32::MAX - 1 + (rhs % 2) as u32)
+    }
+
+    /// Evaluates for `n` matches, saturating at the bounds of `i64`.
+    fn eval(&self, n: i64) -> i64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Matches => n,
+            Expr::Negate(inner) => inner.eval(n).saturating_neg(),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(n), rhs.eval(n));
+                match op {
+                    '+' => lhs.saturating_add(rhs),
+                    '-' => lhs.saturating_sub(rhs),
+                    '*' => lhs.saturating_mul(rhs),
+                    '/' if rhs == 0 => panic!("Division by zero in scoring expression"),
+                    '/' => lhs.saturating_div(rhs),
+                    '%' if rhs == 0 => panic!("Division by zero in scoring expression"),
+                    '%' => lhs.checked_rem(rhs).unwrap_or(0),
+                    '^' if rhs < 0 => 0,
+                    '^' => lhs.saturating_pow(Self::exponent(rhs)),
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+}
+
+/// Recursive descent parser for integer arithmetic over the match count `n`.
+///
+/// ```text
+/// expr   = term (('+' | '-') term)*
+/// term   = power (('*' | '/' | '%') power)*
+/// power  = unary ('^' power)?
+/// unary  = '-' unary | atom
+/// atom   = number | 'n' | '(' expr ')'
+/// ```
+struct ExprParser {
+    tokens: Vec<char>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<char> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn parse_binary(&mut self, ops: &[char], next: fn(&mut Self) -> Result<Expr, String>) -> Result<Expr, String> {
+        let mut lhs = next(self)?;
+        while let Some(op) = self.peek().filter(|c| ops.contains(c)) {
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(next(self)?));
+        }
+        Ok(lhs)
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&['+', '-'], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        self.parse_binary(&['*', '/', '%'], Self::power)
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.unary()?;
+        if self.peek() == Some('^') {
+            self.pos += 1;
+            return Ok(Expr::Binary('^', Box::new(base), Box::new(self.power()?)));
+        }
+        Ok(base)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some('-') {
+            self.pos += 1;
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some('n') => {
+                self.pos += 1;
+                Ok(Expr::Matches)
+            }
+            Some('(') => {
+                self.pos += 1;
+                let inner = self.expr()?;
+                if self.peek() != Some(')') {
+                    return Err(format!("Expected ')' at position {}", self.pos));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+                let digits: String = self.tokens[start..self.pos].iter().collect();
+                digits.parse::<i64>().map(Expr::Number).map_err(|e| e.to_string())
+            }
+            Some(c) => Err(format!("Unexpected '{}' at position {}", c, self.pos)),
+            None => Err(String::from("Unexpected end of expression")),
+        }
+    }
+}
+
+/// A user supplied formula such as `n * n + 1` or `3^(n-1)`, negative results score zero.
+/// Intermediate results saturate at the bounds of `i64` instead of overflowing.
+pub struct Expression {
+    expr: Expr,
+}
+
+impl Expression {
+    pub fn from_string(text: &str) -> Result<Self, String> {
+        let mut parser = ExprParser {
+            tokens: text.chars().filter(|c| !c.is_whitespace()).collect(),
+            pos: 0,
+        };
+        let expr = parser.expr()?;
+        if let Some(c) = parser.peek() {
+            return Err(format!("Unexpected '{}' at position {}", c, parser.pos));
+        }
+        Ok(Self { expr })
+    }
+}
+
+impl ScoringRule for Expression {
+    fn score(&self, matches: u32) -> u64 {
+        if matches == 0 {
+            return 0;
+        }
+        self.expr.eval(matches as i64).max(0) as u64
+    }
+}
+
+/// Parses `doubling`, `linear`, `fibonacci`, `table:1,3,5` or `expr:<formula>`.
+pub fn from_string(text: &str) -> Box<dyn ScoringRule> {
+    if let Some(table) = text.strip_prefix("table:") {
+        let points: Vec<u64> = table
+            .split(',')
+            .map(|p| p.trim().parse::<u64>().expect("Error parsing lookup table entry"))
+            .collect();
+        return Box::new(LookupTable { points });
+    }
+    if let Some(formula) = text.strip_prefix("expr:") {
+        return Box::new(Expression::from_string(formula).expect("Error parsing scoring expression"));
+    }
+
+    match text {
+        "doubling" => Box::new(Doubling),
+        "linear" => Box::new(Linear),
+        "fibonacci" => Box::new(Fibonacci),
+        _ => panic!("Unknown scoring rule, expected doubling, linear, fibonacci, table:<points> or expr:<formula>"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expression(text: &str) -> Expression {
+        Expression::from_string(text).unwrap()
+    }
+
+    fn scores(rule: &dyn ScoringRule) -> Vec<u64> {
+        (1..=5).map(|matches| rule.score(matches)).collect()
+    }
+
+    #[test]
+    fn builtin_rules_score_their_sequences() {
+        assert_eq!(scores(&Doubling), vec![1, 2, 4, 8, 16]);
+        assert_eq!(scores(&Linear), vec![1, 2, 3, 4, 5]);
+        assert_eq!(scores(&Fibonacci), vec![1, 1, 2, 3, 5]);
+    }
+
+    #[test]
+    fn lookup_table_clamps_to_the_last_entry() {
+        let table = LookupTable { points: vec![1, 3, 5] };
+        assert_eq!(scores(&table), vec![1, 3, 5, 5, 5]);
+    }
+
+    #[test]
+    fn expressions_follow_precedence() {
+        assert_eq!(scores(&expression("n*n+1")), vec![2, 5, 10, 17, 26]);
+        assert_eq!(scores(&expression("2^n-1")), vec![1, 3, 7, 15, 31]);
+        // unary minus binds tighter than `^`, as in the grammar above
+        assert_eq!(expression("-2^2+5").score(1), 9);
+        assert_eq!(expression("0-2^2+5").score(1), 1);
+        assert_eq!(expression("2^3^2").score(1), 512);
+        assert_eq!(expression("(n+1)*2").score(3), 8);
+    }
+
+    #[test]
+    fn no_matches_score_nothing() {
+        let rules: Vec<Box<dyn ScoringRule>> = vec![
+            Box::new(Doubling),
+            Box::new(Linear),
+            Box::new(Fibonacci),
+            Box::new(LookupTable { points: vec![1, 3, 5] }),
+            Box::new(expression("n+1")),
+        ];
+        for rule in rules {
+            assert_eq!(rule.score(0), 0);
+        }
+    }
+
+    #[test]
+    fn large_scores_saturate() {
+        assert_eq!(Doubling.score(64), 1 << 63);
+        assert_eq!(Doubling.score(65), u64::MAX);
+        assert_eq!(Fibonacci.score(128), u64::MAX);
+        assert_eq!(expression("n^30").score(100), i64::MAX as u64);
+        assert_eq!(expression("0 - n^30").score(100), 0);
+    }
+
+    #[test]
+    fn large_exponents_keep_their_parity() {
+        assert_eq!(expression("(0-1)^(n*4294967296)").score(1), 1);
+        assert_eq!(expression("(0-1)^(n*4294967296+1)+2").score(1), 1);
+        assert_eq!(expression("1^(n*4294967296)").score(3), 1);
+    }
+}
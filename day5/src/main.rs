@@ -2,8 +2,12 @@ use core::panic;
 use std::cmp;
 use std::env;
 use std::fs;
+use std::io::{self, Write};
 use std::ops::Range;
-use std::result;
+
+mod piecewise;
+
+use piecewise::PiecewiseMap;
 
 #[derive(Debug, PartialEq)]
 enum InformationType {
@@ -72,8 +76,12 @@ impl MappingRule {
             return None;
         }
 
-        let index_into_source_range = value - self.source_range.start;
-        return Some(self.destination_range.start + index_into_source_range);
+        Some(value + self.offset())
+    }
+
+    /// Distance a value moves when it is translated by this rule.
+    fn offset(&self) -> isize {
+        self.destination_range.start - self.source_range.start
     }
 
 }
@@ -91,7 +99,7 @@ trait Overlap<T> {
 
 impl<T> Overlap<T> for Range<T> where T: Ord + Copy {
     fn overlap(&self, other: &Range<T>) -> Range<T> {
-        if self.start < other.end && self.end >= other.start {
+        if self.start < other.end && self.end > other.start {
             Range {start: cmp::max(self.start, other.start), end: cmp::min(self.end, other.end)}
         } else {
             Range {start: self.start, end: self.start}
@@ -132,24 +140,36 @@ impl InformationMapping {
         value
     }
 
-    fn translate_range(&self, value_range: Range<isize>) -> Vec<Range<isize>> {
-
-        let mut resulting_ranges = Vec::new();
+    /// Translates a set of ranges. Values covered by a rule are moved by that
+    /// rule's offset, the parts of the ranges no rule covers keep their values.
+    /// Like `translate`, the first matching rule wins.
+    fn translate_ranges(&self, value_ranges: &[Range<isize>]) -> Vec<Range<isize>> {
+        let mut translated : Vec<Range<isize>> = Vec::new();
+        let mut untranslated : Vec<Range<isize>> = value_ranges.iter().filter(|r| !r.is_empty()).cloned().collect();
 
         for rule in &self.rules {
-            let overlap = value_range.overlap(&rule.source_range);
-            if overlap.is_empty() {
-                continue;
-            }
+            let mut remaining : Vec<Range<isize>> = Vec::new();
 
-            let res_start = rule.translate(overlap.start).unwrap();
-            let res_end = rule.translate(overlap.end).unwrap();
+            for r in &untranslated {
+                let overlap = r.overlap(&rule.source_range);
+                if overlap.is_empty() {
+                    remaining.push(r.clone());
+                    continue;
+                }
 
-            resulting_ranges.push(Range {start: res_start, end: res_end});
+                if r.start < overlap.start {
+                    remaining.push(Range { start: r.start, end: overlap.start });
+                }
+                translated.push(Range { start: overlap.start + rule.offset(), end: overlap.end + rule.offset() });
+                if overlap.end < r.end {
+                    remaining.push(Range { start: overlap.end, end: r.end });
+                }
+            }
+            untranslated = remaining;
         }
 
-
-        resulting_ranges
+        translated.append(&mut untranslated);
+        translated
     }
 
 }
@@ -173,101 +193,207 @@ impl Almanac {
             mappings
         }
     }
+
+    /// All mappings folded into one map from seed to location.
+    fn composed(&self) -> PiecewiseMap {
+        PiecewiseMap::compose_all(&self.mappings)
+    }
+
+    /// The seed line read as `start length` pairs.
+    fn seed_ranges(&self) -> Vec<Range<isize>> {
+        self.seeds.chunks(2).map(|pair| Range { start: pair[0], end: pair[0] + pair[1] }).collect()
+    }
 }
 
-fn riddle_part_one(file_path: &String) {
+/// Like part two, `stepwise` skips the composed map.
+fn riddle_part_one(file_path: &String, stepwise: bool) {
     let text = fs::read_to_string(file_path).expect("Error reading file");
 
     let almanac = Almanac::from_string(&text);
 
-    let mut locations : Vec<isize> = Vec::new();
+    let locations : Vec<isize> = if stepwise {
+        almanac.seeds.iter().map(|seed| almanac.mappings.iter().fold(*seed, |value, mapping| mapping.translate(value))).collect()
+    } else {
+        let seed_to_location = almanac.composed();
+        almanac.seeds.iter().map(|seed| seed_to_location.translate(*seed)).collect()
+    };
+    println!("Lowest Destination: {:?}", locations.iter().min().unwrap());
+
+}
 
-    for seed in almanac.seeds {
-        
-        let mut destination_value = seed;
+/// Without `stepwise` the seed ranges are looked up in the composed map,
+/// otherwise they are pushed through every mapping in turn.
+fn riddle_part_two(file_path: &String, stepwise: bool) {
+    let text = fs::read_to_string(file_path).expect("Error reading file");
 
-        for mapping in &almanac.mappings {
-            destination_value = mapping.translate(destination_value);
+    let almanac = Almanac::from_string(&text);
+
+    let mut current_gen : Vec<Range<isize>> = almanac.seed_ranges();
+    if stepwise {
+        for convert in &almanac.mappings {
+            current_gen = convert.translate_ranges(&current_gen);
         }
-        locations.push(destination_value);
+    } else {
+        let seed_to_location = almanac.composed();
+        current_gen = current_gen.iter().flat_map(|r| seed_to_location.translate_range(r)).collect();
     }
-    println!("Lowest Destination: {:?}", locations.iter().min().unwrap());
 
+    println!("Lowest Destination: {:?}", current_gen.iter().map(|r| r.start).min().unwrap());
 }
 
-fn riddle_part_two(file_path: &String) {
+/// Prints the composed seed to location map in almanac syntax.
+fn compose(file_path: &String) {
     let text = fs::read_to_string(file_path).expect("Error reading file");
 
     let almanac = Almanac::from_string(&text);
+    let seed_to_location = almanac.composed();
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "seed-to-location map:").expect("Error writing map");
+    write!(stdout, "{}", seed_to_location).expect("Composed map does not fit into almanac rules");
+}
 
-    // Transform the seeds into their ranges
-    let mut current_gen : Vec<Range<isize>> = Vec::new();
-    for index in (0..almanac.seeds.len()).step_by(2) {
-        let start  = almanac.seeds[index];
-        let len = almanac.seeds[index + 1];
-        current_gen.push(Range {start: start, end: start + len });
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        panic!("Provide the riddle part and the input text file!");
     }
+    let file_path = args.get(2).unwrap();
+    let stepwise = match args.get(3).map(|arg| arg.as_str()) {
+        None => false,
+        Some("--stepwise") => true,
+        Some(arg) => panic!("Unknown option {}", arg)
+    };
 
-    for convert in &almanac.mappings {
-        
-        let mut converted : Vec<Range<isize>> = Vec::new();
-        let mut unconverted = current_gen.clone();
+    match args[1].as_str() {
+        "1" => {
+            riddle_part_one(file_path, stepwise);
+        }
+        "2" => {
+            riddle_part_two(file_path, stepwise);
+        }
+        "compose" => {
+            compose(file_path);
+        }
+        _ => {
+            panic!("Unknown command, expected 1, 2 or compose");
+        }
+    };
+}
 
-        for rule in &convert.rules {
-            let convert_range = &rule.source_range;
-            let offset = rule.destination_range.start - rule.source_range.start;
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
 
-            let mut new_unconverted = Vec::<Range<isize>>::new();
+    fn mapping(rules: &[&str]) -> InformationMapping {
+        InformationMapping {
+            source_type: InformationType::Seed,
+            destination_type: InformationType::Soil,
+            rules: rules.iter().map(|rule| MappingRule::from_string(rule)).collect()
+        }
+    }
 
-            for r in &unconverted {
-                let overlap = r.overlap(&convert_range);
+    fn sorted(mut ranges: Vec<Range<isize>>) -> Vec<Range<isize>> {
+        ranges.sort_by_key(|r| r.start);
+        ranges
+    }
 
-                let left = Range { start: r.start, end: overlap.start};
-                if left.end > left.start {
-                    new_unconverted.push(left);
-                }
+    #[test]
+    fn passes_unmapped_parts_through() {
+        // 10..20 moves to 100..110
+        let mapping = mapping(&["100 10 10"]);
+        assert_eq!(sorted(mapping.translate_ranges(&[5..25])), vec![5..10, 20..25, 100..110]);
+        assert_eq!(mapping.translate_ranges(&[30..40]), vec![30..40]);
+    }
 
-                if overlap.end > overlap.start {
-                    converted.push(Range { start: overlap.start + offset, end: overlap.end + offset});
-                }
+    #[test]
+    fn range_ending_at_rule_end() {
+        let mapping = mapping(&["100 10 10"]);
+        assert_eq!(mapping.translate_ranges(&[15..20]), vec![105..110]);
+        assert_eq!(sorted(mapping.translate_ranges(&[10..21])), vec![20..21, 100..110]);
+        assert_eq!(mapping.translate_ranges(&[20..30]), vec![20..30]);
+    }
 
-                let right = Range {start: overlap.end, end: r.end};
-                if right.end > right.start {
-                    new_unconverted.push(right);
-                }
+    #[test]
+    fn matches_point_translation() {
+        let mapping = mapping(&["50 98 2", "52 50 48"]);
+        let translated = mapping.translate_ranges(&[40..110, 0..0]);
 
-            }
-            unconverted = new_unconverted;
-        }
-        current_gen = Vec::new();
-        current_gen.append(&mut converted);
-        current_gen.append(&mut unconverted);
+        let mut expected : Vec<isize> = (40..110).map(|value| mapping.translate(value)).collect();
+        let mut actual : Vec<isize> = translated.iter().flat_map(|r| r.clone()).collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
     }
 
-    dbg!(current_gen.iter().min_by(|x, y| x.start.cmp(&y.start)));
-}
+    const SAMPLE : &str = "seeds: 79 14 55 13
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        panic!("Provide the input text file!");
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
+
+    fn sample_almanac() -> Almanac {
+        Almanac::from_string(SAMPLE)
     }
-    let riddle_num: u32 = args
-        .get(1)
-        .unwrap()
-        .parse()
-        .expect("Error parsing ridle num");
 
+    #[test]
+    fn composed_map_matches_stepwise_translation() {
+        let almanac = sample_almanac();
+        let seed_to_location = almanac.composed();
 
-    match riddle_num {
-        1 => {
-            riddle_part_one(args.get(2).unwrap());
-        }
-        2 => {
-            riddle_part_two(args.get(2).unwrap());
+        for seed in 0..120 {
+            let stepwise = almanac.mappings.iter().fold(seed, |value, mapping| mapping.translate(value));
+            assert_eq!(seed_to_location.translate(seed), stepwise);
         }
-        _ => {
-            panic!("Unknown riddle part number");
+
+        let mut stepwise = almanac.seed_ranges();
+        for mapping in &almanac.mappings {
+            stepwise = mapping.translate_ranges(&stepwise);
         }
-    };
+        let composed : Vec<Range<isize>> = almanac.seed_ranges().iter().flat_map(|r| seed_to_location.translate_range(r)).collect();
+        let mut expected : Vec<isize> = stepwise.into_iter().flatten().collect();
+        let mut actual : Vec<isize> = composed.into_iter().flatten().collect();
+        expected.sort();
+        actual.sort();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn exported_map_parses_back() {
+        let seed_to_location = sample_almanac().composed();
+        let exported = InformationMapping::from_text(&format!("seed-to-location map:\n{}", seed_to_location));
+
+        assert_eq!(exported.source_type, InformationType::Seed);
+        assert_eq!(exported.destination_type, InformationType::Location);
+        assert_eq!(PiecewiseMap::from_mapping(&exported), seed_to_location);
+    }
 }
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::InformationMapping;
+
+/// A function over all of `isize` that adds a constant offset per segment.
+///
+/// Segment `i` starts at `segments[i].0` and ends where the next one starts,
+/// the first segment starts at `isize::MIN` and the last one runs to
+/// `isize::MAX`. Neighbouring segments always have different offsets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiecewiseMap {
+    segments: Vec<(isize, isize)>
+}
+
+impl PiecewiseMap {
+    pub fn identity() -> Self {
+        Self {
+            segments: vec![(isize::MIN, 0)]
+        }
+    }
+
+    /// Builds the map from sorted `(start, offset)` pieces, dropping starts
+    /// that do not change the offset.
+    fn from_pieces(pieces: Vec<(isize, isize)>) -> Self {
+        let mut segments : Vec<(isize, isize)> = Vec::new();
+        for (start, offset) in pieces {
+            match segments.last_mut() {
+                Some(last) if last.0 == start => last.1 = offset,
+                Some(last) if last.1 == offset => continue,
+                _ => segments.push((start, offset))
+            }
+        }
+        // A replaced offset can make two neighbours equal again
+        segments.dedup_by(|next, prev| prev.1 == next.1);
+        Self {
+            segments
+        }
+    }
+
+    /// The map of a single mapping, where the first matching rule wins.
+    pub fn from_mapping(mapping: &InformationMapping) -> Self {
+        let mut breakpoints : Vec<isize> = mapping.rules.iter().flat_map(|rule| [rule.source_range.start, rule.source_range.end]).collect();
+        breakpoints.push(isize::MIN);
+        breakpoints.sort();
+        breakpoints.dedup();
+
+        let pieces = breakpoints.into_iter().map(|start| {
+            let offset = mapping.rules.iter().find(|rule| rule.source_range.contains(&start)).map(|rule| rule.offset()).unwrap_or(0);
+            (start, offset)
+        }).collect();
+        Self::from_pieces(pieces)
+    }
+
+    /// Folds all mappings, in order, into a single map.
+    pub fn compose_all(mappings: &[InformationMapping]) -> Self {
+        mappings.iter().fold(Self::identity(), |map, mapping| map.then(&Self::from_mapping(mapping)))
+    }
+
+    fn segment_end(&self, index: usize) -> isize {
+        self.segments.get(index + 1).map(|segment| segment.0).unwrap_or(isize::MAX)
+    }
+
+    fn segment_index(&self, value: isize) -> usize {
+        self.segments.partition_point(|segment| segment.0 <= value) - 1
+    }
+
+    /// The map that applies `self` first and `next` afterwards.
+    pub fn then(&self, next: &PiecewiseMap) -> Self {
+        let mut pieces : Vec<(isize, isize)> = Vec::new();
+
+        for (index, (start, offset)) in self.segments.iter().enumerate() {
+            let end = self.segment_end(index);
+            let image_start = start.saturating_add(*offset);
+            let image_end = end.saturating_add(*offset);
+
+            let mut next_index = next.segment_index(image_start);
+            pieces.push((*start, offset + next.segments[next_index].1));
+
+            next_index += 1;
+            while next_index < next.segments.len() && next.segments[next_index].0 < image_end {
+                let (next_start, next_offset) = next.segments[next_index];
+                pieces.push((next_start - offset, offset + next_offset));
+                next_index += 1;
+            }
+        }
+        Self::from_pieces(pieces)
+    }
+
+    pub fn translate(&self, value: isize) -> isize {
+        value + self.segments[self.segment_index(value)].1
+    }
+
+    /// Translates a range into one range per segment it touches.
+    pub fn translate_range(&self, value_range: &Range<isize>) -> Vec<Range<isize>> {
+        let mut translated : Vec<Range<isize>> = Vec::new();
+        if value_range.is_empty() {
+            return translated;
+        }
+
+        let mut index = self.segment_index(value_range.start);
+        let mut start = value_range.start;
+        while start < value_range.end {
+            let end = self.segment_end(index).min(value_range.end);
+            let offset = self.segments[index].1;
+            translated.push(Range { start: start + offset, end: end + offset });
+
+            start = end;
+            index += 1;
+        }
+        translated
+    }
+}
+
+/// Writes the rules of an almanac block, one `destination source length`
+/// rule per segment that moves its values. The header is up to the caller,
+/// since the map does not know which types it connects. Fails when a rule
+/// does not fit into `isize`, like a moved segment starting at `isize::MIN`.
+impl fmt::Display for PiecewiseMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, (start, offset)) in self.segments.iter().enumerate() {
+            if *offset == 0 {
+                continue;
+            }
+            let destination = start.checked_add(*offset).ok_or(fmt::Error)?;
+            let length = self.segment_end(index).checked_sub(*start).ok_or(fmt::Error)?;
+            writeln!(f, "{} {} {}", destination, start, length)?;
+        }
+        Ok(())
+    }
+}